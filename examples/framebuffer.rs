@@ -5,13 +5,12 @@ extern crate yaglw;
 use gl::types::*;
 use sdl2::EventPump;
 use sdl2::event::Event;
-use std::mem;
 
 use yaglw::framebuffer::Framebuffer;
 use yaglw::gl_context::GLContext;
-use yaglw::shader::Shader;
-use yaglw::texture::Texture2D;
-use yaglw::vertex_buffer::{ArrayHandle, GLArray, GLBuffer, GLType, VertexAttribData, DrawMode};
+use yaglw::shader::{Shader, ShaderStage};
+use yaglw::texture::{Texture2D, TextureUnit};
+use yaglw::vertex_buffer::{ArrayHandle, GLArray, GLBuffer, GLType, AttribLocation, VertexAttribData, DrawMode};
 
 const WINDOW_WIDTH: i32 = 800;
 const WINDOW_HEIGHT: i32 = 600;
@@ -90,16 +89,9 @@ pub fn main() {
   let window = make_window(&video);
   let mut event_pump = sdl.event_pump().unwrap();
 
-  let _sdl_gl_context = window.gl_create_context().unwrap();
+  let sdl_gl_context = window.gl_create_context().unwrap();
 
-  // Load the OpenGL function pointers.
-  gl::load_with(|s| unsafe {
-    mem::transmute(video.gl_get_proc_address(s))
-  });
-
-  let mut gl = unsafe {
-    GLContext::new()
-  };
+  let mut gl = GLContext::from_sdl2(&video, &sdl_gl_context).unwrap();
 
   let vertices = [
     Vertex {
@@ -116,38 +108,42 @@ pub fn main() {
     },
   ];
 
-  let mut vbo = GLBuffer::new(&mut gl, 3);
+  let mut vbo = GLBuffer::new(&mut gl, 3).unwrap();
   vbo.push(&mut gl, &vertices);
 
   let attribs = [
     VertexAttribData {
-      name: "position",
+      location: AttribLocation::Name("position"),
       size: 2,
       unit: GLType::Float,
       divisor: 0,
+      offset: None,
+      normalized: false,
     },
     VertexAttribData {
-      name: "color",
+      location: AttribLocation::Name("color"),
       size: 3,
       unit: GLType::Float,
       divisor: 0,
+      offset: None,
+      normalized: false,
     },
   ];
 
   let components = [
-    ((gl::VERTEX_SHADER, VERTEX_SHADER)),
-    ((gl::FRAGMENT_SHADER, FRAGMENT_SHADER)),
+    ((ShaderStage::Vertex, VERTEX_SHADER)),
+    ((ShaderStage::Fragment, FRAGMENT_SHADER)),
   ];
 
-  let shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s))));
+  let shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s)))).unwrap();
   shader.use_shader(&mut gl);
 
   let components = [
-    ((gl::VERTEX_SHADER, DEFERRED_VERTEX_SHADER)),
-    ((gl::FRAGMENT_SHADER, DEFERRED_FRAGMENT_SHADER)),
+    ((ShaderStage::Vertex, DEFERRED_VERTEX_SHADER)),
+    ((ShaderStage::Fragment, DEFERRED_FRAGMENT_SHADER)),
   ];
 
-  let mut deferred_shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s))));
+  let mut deferred_shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s)))).unwrap();
   deferred_shader.use_shader(&mut gl);
 
   let vao =
@@ -157,21 +153,21 @@ pub fn main() {
       &attribs,
       DrawMode::Triangles,
       vbo,
-    );
+    ).unwrap();
   vao.bind(&mut gl);
 
   let empty_vao = ArrayHandle::new(&gl);
 
-  match gl.get_error() {
-    gl::NO_ERROR => {},
-    err => {
-      println!("OpenGL error 0x{:x} in setup 1", err);
+  match gl.check_errors() {
+    Ok(()) => {},
+    Err(err) => {
+      println!("OpenGL error in setup 1: {}", err);
       return;
     },
   }
 
-  let mut fbo = Framebuffer::new(&gl);
-  let colors = Texture2D::new(&gl);
+  let mut fbo = Framebuffer::new(&gl).unwrap();
+  let colors = Texture2D::new(&gl).unwrap();
 
   unsafe {
     gl::ActiveTexture(gl::TEXTURE0);
@@ -187,24 +183,21 @@ pub fn main() {
   fbo.bind(&mut gl);
   fbo.attach_2d(&gl, gl::COLOR_ATTACHMENT0, &colors);
 
-  match gl.get_error() {
-    gl::NO_ERROR => {},
-    err => {
-      println!("OpenGL error 0x{:x} in setup 2", err);
+  match gl.check_errors() {
+    Ok(()) => {},
+    Err(err) => {
+      println!("OpenGL error in setup 2: {}", err);
       return;
     },
   }
 
-  let color_uniform = deferred_shader.get_uniform_location("colors");
   deferred_shader.use_shader(&mut gl);
-  unsafe {
-    gl::Uniform1i(color_uniform, 0);
-  }
+  deferred_shader.set_uniform(&mut gl, "colors", &TextureUnit::default()).unwrap();
 
-  match gl.get_error() {
-    gl::NO_ERROR => {},
-    err => {
-      println!("OpenGL error 0x{:x} in setup", err);
+  match gl.check_errors() {
+    Ok(()) => {},
+    Err(err) => {
+      println!("OpenGL error in setup: {}", err);
       return;
     },
   }
@@ -5,11 +5,10 @@ extern crate yaglw;
 use gl::types::*;
 use sdl2::EventPump;
 use sdl2::event::Event;
-use std::mem;
 
 use yaglw::gl_context::GLContext;
-use yaglw::shader::Shader;
-use yaglw::vertex_buffer::{GLArray, GLBuffer, GLType, VertexAttribData, DrawMode};
+use yaglw::shader::{Shader, ShaderStage};
+use yaglw::vertex_buffer::{GLArray, GLBuffer, GLType, AttribLocation, VertexAttribData, DrawMode};
 
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -53,16 +52,9 @@ pub fn main() {
   let window = make_window(&video);
   let mut event_pump = sdl.event_pump().unwrap();
 
-  let _sdl_gl_context = window.gl_create_context().unwrap();
+  let sdl_gl_context = window.gl_create_context().unwrap();
 
-  // Load the OpenGL function pointers.
-  gl::load_with(|s| unsafe {
-    mem::transmute(video.gl_get_proc_address(s))
-  });
-
-  let mut gl = unsafe {
-    GLContext::new()
-  };
+  let mut gl = GLContext::from_sdl2(&video, &sdl_gl_context).unwrap();
 
   let vertices = [
     Vertex {
@@ -79,30 +71,34 @@ pub fn main() {
     },
   ];
 
-  let mut vbo = GLBuffer::new(&mut gl, 3);
+  let mut vbo = GLBuffer::new(&mut gl, 3).unwrap();
   vbo.push(&mut gl, &vertices);
 
   let attribs = [
     VertexAttribData {
-      name: "position",
+      location: AttribLocation::Name("position"),
       size: 2,
       unit: GLType::Float,
       divisor: 0,
+      offset: None,
+      normalized: false,
     },
     VertexAttribData {
-      name: "color",
+      location: AttribLocation::Name("color"),
       size: 3,
       unit: GLType::Float,
       divisor: 0,
+      offset: None,
+      normalized: false,
     },
   ];
 
   let components = [
-    ((gl::VERTEX_SHADER, VERTEX_SHADER)),
-    ((gl::FRAGMENT_SHADER, FRAGMENT_SHADER)),
+    ((ShaderStage::Vertex, VERTEX_SHADER)),
+    ((ShaderStage::Fragment, FRAGMENT_SHADER)),
   ];
 
-  let shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s))));
+  let shader = Shader::new(&gl, components.iter().map(|&(ty, s)| (ty, String::from(s)))).unwrap();
   shader.use_shader(&mut gl);
 
   let vao =
@@ -112,13 +108,13 @@ pub fn main() {
       &attribs,
       DrawMode::Triangles,
       vbo,
-    );
+    ).unwrap();
   vao.bind(&mut gl);
 
-  match gl.get_error() {
-    gl::NO_ERROR => {},
-    err => {
-      println!("OpenGL error 0x{:x} in setup", err);
+  match gl.check_errors() {
+    Ok(()) => {},
+    Err(err) => {
+      println!("OpenGL error in setup: {}", err);
       return;
     },
   }
@@ -1,6 +1,6 @@
 use gl;
 use gl::types::*;
-use gl_context::GLContext;
+use gl_context::{GLContext, GlError};
 use std::marker::PhantomData;
 
 use texture::Texture2D;
@@ -11,16 +11,20 @@ pub struct Framebuffer<'a> {
 }
 
 impl<'a> Framebuffer<'a> {
-  pub fn new<'b:'a>(_gl: &'a GLContext) -> Framebuffer<'b> {
+  pub fn new<'b:'a>(gl: &'a GLContext) -> Result<Framebuffer<'b>, GlError> {
     let mut gl_id = 0;
     unsafe {
       gl::GenFramebuffers(1, &mut gl_id);
     }
 
-    Framebuffer {
+    let framebuffer = Framebuffer {
       gl_id: gl_id,
       phantom: PhantomData,
-    }
+    };
+
+    gl.check_errors()?;
+
+    Ok(framebuffer)
   }
 
   pub fn bind(&mut self, _gl: &mut GLContext) {
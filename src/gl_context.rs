@@ -1,8 +1,22 @@
 use gl;
 use gl::types::*;
 use std;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::ptr;
 use std::str;
 
+#[cfg(feature = "sdl2")]
+use sdl2;
+#[cfg(feature = "glutin")]
+use glutin;
+
+thread_local!(static CONTEXT_EXISTS: Cell<bool> = Cell::new(false));
+
 unsafe fn from_c_str<'a>(s: *const u8) -> &'a str {
   let mut len = 0;
   {
@@ -16,14 +30,178 @@ unsafe fn from_c_str<'a>(s: *const u8) -> &'a str {
   str::from_utf8_unchecked(std::slice::from_raw_parts(s, len))
 }
 
-pub struct GLContext;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContextError {
+  /// There is already a `GLContext` on this thread.
+  AlreadyExists,
+  /// The windowing system's OpenGL context isn't current on this thread.
+  NotCurrent,
+}
+
+/// An error raised by OpenGL, as returned by `glGetError`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlError {
+  InvalidEnum,
+  InvalidValue,
+  InvalidOperation,
+  StackOverflow,
+  StackUnderflow,
+  OutOfMemory,
+  InvalidFramebufferOperation,
+  /// An error code this enum doesn't know about.
+  Unknown(GLenum),
+}
+
+impl GlError {
+  /// Returns `None` for `GL_NO_ERROR`.
+  pub fn from_gl(err: GLenum) -> Option<GlError> {
+    match err {
+      gl::NO_ERROR                      => None,
+      gl::INVALID_ENUM                  => Some(GlError::InvalidEnum),
+      gl::INVALID_VALUE                 => Some(GlError::InvalidValue),
+      gl::INVALID_OPERATION             => Some(GlError::InvalidOperation),
+      gl::STACK_OVERFLOW                => Some(GlError::StackOverflow),
+      gl::STACK_UNDERFLOW               => Some(GlError::StackUnderflow),
+      gl::OUT_OF_MEMORY                 => Some(GlError::OutOfMemory),
+      gl::INVALID_FRAMEBUFFER_OPERATION => Some(GlError::InvalidFramebufferOperation),
+      err                               => Some(GlError::Unknown(err)),
+    }
+  }
+
+  pub fn gl_enum(&self) -> GLenum {
+    match *self {
+      GlError::InvalidEnum                 => gl::INVALID_ENUM,
+      GlError::InvalidValue                => gl::INVALID_VALUE,
+      GlError::InvalidOperation            => gl::INVALID_OPERATION,
+      GlError::StackOverflow               => gl::STACK_OVERFLOW,
+      GlError::StackUnderflow              => gl::STACK_UNDERFLOW,
+      GlError::OutOfMemory                 => gl::OUT_OF_MEMORY,
+      GlError::InvalidFramebufferOperation => gl::INVALID_FRAMEBUFFER_OPERATION,
+      GlError::Unknown(err)                => err,
+    }
+  }
+}
+
+impl fmt::Display for GlError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      GlError::Unknown(err) => write!(f, "unknown OpenGL error 0x{:x}", err),
+      err => write!(f, "{:?} (0x{:x})", err, err.gl_enum()),
+    }
+  }
+}
+
+impl std::error::Error for GlError {}
+
+/// A kind of incoherent memory access to make visible, for `GLContext::memory_barrier`.
+/// Each variant names how the data will be read *after* the barrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryBarrier {
+  VertexAttribArray,
+  ElementArray,
+  Uniform,
+  TextureFetch,
+  ShaderImageAccess,
+  Command,
+  PixelBuffer,
+  TextureUpdate,
+  BufferUpdate,
+  Framebuffer,
+  TransformFeedback,
+  AtomicCounter,
+  ShaderStorage,
+  All,
+}
+
+impl MemoryBarrier {
+  pub fn gl_bit(&self) -> GLbitfield {
+    match *self {
+      MemoryBarrier::VertexAttribArray => gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+      MemoryBarrier::ElementArray      => gl::ELEMENT_ARRAY_BARRIER_BIT,
+      MemoryBarrier::Uniform           => gl::UNIFORM_BARRIER_BIT,
+      MemoryBarrier::TextureFetch      => gl::TEXTURE_FETCH_BARRIER_BIT,
+      MemoryBarrier::ShaderImageAccess => gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+      MemoryBarrier::Command           => gl::COMMAND_BARRIER_BIT,
+      MemoryBarrier::PixelBuffer       => gl::PIXEL_BUFFER_BARRIER_BIT,
+      MemoryBarrier::TextureUpdate     => gl::TEXTURE_UPDATE_BARRIER_BIT,
+      MemoryBarrier::BufferUpdate      => gl::BUFFER_UPDATE_BARRIER_BIT,
+      MemoryBarrier::Framebuffer       => gl::FRAMEBUFFER_BARRIER_BIT,
+      MemoryBarrier::TransformFeedback => gl::TRANSFORM_FEEDBACK_BARRIER_BIT,
+      MemoryBarrier::AtomicCounter     => gl::ATOMIC_COUNTER_BARRIER_BIT,
+      MemoryBarrier::ShaderStorage     => gl::SHADER_STORAGE_BARRIER_BIT,
+      MemoryBarrier::All               => gl::ALL_BARRIER_BITS,
+    }
+  }
+}
+
+/// Token for the OpenGL context that is current on this thread.
+/// At most one exists per thread at a time.
+pub struct GLContext {
+  /// KHR_debug message IDs that shouldn't be logged.
+  /// Boxed so the debug callback can hold a stable pointer to it.
+  ignored_debug_ids: Box<HashSet<GLuint>>,
+  // Not `Send`: OpenGL contexts are bound to a thread.
+  phantom: PhantomData<*mut ()>,
+}
 
-// TODO(bfops): Safely create GLContext from existing ones, e.g. sdl2::video::GLContext.
 impl GLContext {
+  /// Panics if there is already a `GLContext` on this thread.
+  /// Unsafe because the caller must have loaded the OpenGL function pointers,
+  /// and made an OpenGL context current on this thread.
   pub unsafe fn new() -> GLContext {
-    // TODO(cgaebel): Have a thread-local variable checking whether or not
-    // there is only one GLContext, and fail if there's more than one.
-    GLContext
+    match GLContext::claim() {
+      Ok(gl) => gl,
+      Err(err) => panic!("GLContext::new: {:?}", err),
+    }
+  }
+
+  fn claim() -> Result<GLContext, ContextError> {
+    CONTEXT_EXISTS.with(|exists| {
+      if exists.get() {
+        Err(ContextError::AlreadyExists)
+      } else {
+        exists.set(true);
+        Ok(GLContext {
+          ignored_debug_ids: Box::new(HashSet::new()),
+          phantom: PhantomData,
+        })
+      }
+    })
+  }
+
+  /// Loads the OpenGL function pointers through SDL2 and wraps its current context.
+  #[cfg(feature = "sdl2")]
+  pub fn from_sdl2(
+    video: &sdl2::VideoSubsystem,
+    context: &sdl2::video::GLContext,
+  ) -> Result<GLContext, ContextError> {
+    if !context.is_current() {
+      return Err(ContextError::NotCurrent);
+    }
+
+    let gl = GLContext::claim()?;
+
+    gl::load_with(|s| unsafe {
+      std::mem::transmute(video.gl_get_proc_address(s))
+    });
+
+    Ok(gl)
+  }
+
+  /// Loads the OpenGL function pointers through glutin and wraps the window's current context.
+  #[cfg(feature = "glutin")]
+  pub fn from_glutin(window: &glutin::Window) -> Result<GLContext, ContextError> {
+    if !window.is_current() {
+      return Err(ContextError::NotCurrent);
+    }
+
+    let gl = GLContext::claim()?;
+
+    gl::load_with(|s| unsafe {
+      std::mem::transmute(window.get_proc_address(s))
+    });
+
+    Ok(gl)
   }
 
   /// Stops the processing of any triangles hidden from view when rendering.
@@ -94,9 +272,164 @@ impl GLContext {
     }
   }
 
-  pub fn get_error(&self) -> GLuint {
+  /// A `glGetString` value, e.g. `gl::VENDOR` or `gl::RENDERER`.
+  pub fn get_string(&self, name: GLenum) -> String {
     unsafe {
-      gl::GetError()
+      let s = gl::GetString(name);
+      if s.is_null() {
+        return String::new();
+      }
+      String::from(from_c_str(s))
     }
   }
+
+  /// Routes KHR_debug messages from the driver into the `log` crate.
+  /// Output is synchronous, so messages are logged from inside the offending GL call.
+  /// Returns false and does nothing if KHR_debug isn't available.
+  pub fn enable_debug_output(&mut self) -> bool {
+    if !gl::DebugMessageCallback::is_loaded() {
+      return false;
+    }
+
+    unsafe {
+      gl::Enable(gl::DEBUG_OUTPUT);
+      gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+      gl::DebugMessageCallback(
+        debug_callback,
+        &*self.ignored_debug_ids as *const HashSet<GLuint> as *mut c_void,
+      );
+    }
+
+    true
+  }
+
+  pub fn disable_debug_output(&mut self) {
+    if !gl::DebugMessageCallback::is_loaded() {
+      return;
+    }
+
+    unsafe {
+      gl::Disable(gl::DEBUG_OUTPUT);
+      gl::Disable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+    }
+  }
+
+  /// Stop logging debug messages with this ID.
+  pub fn ignore_debug_message(&mut self, id: GLuint) {
+    self.ignored_debug_ids.insert(id);
+  }
+
+  /// Resume logging debug messages with this ID.
+  pub fn unignore_debug_message(&mut self, id: GLuint) {
+    self.ignored_debug_ids.remove(&id);
+  }
+
+  /// Make writes from shaders (e.g. to storage buffers or images) visible to the given kinds of reads.
+  pub fn memory_barrier(&mut self, barriers: &[MemoryBarrier]) {
+    let bits = barriers.iter().fold(0, |bits, b| bits | b.gl_bit());
+    unsafe {
+      gl::MemoryBarrier(bits);
+    }
+  }
+
+  /// Pops the oldest error off OpenGL's error queue.
+  pub fn get_error(&self) -> Option<GlError> {
+    GlError::from_gl(unsafe { gl::GetError() })
+  }
+
+  /// Drains OpenGL's whole error queue.
+  /// Returns the oldest error, and logs any others.
+  pub fn check_errors(&self) -> Result<(), GlError> {
+    let first = match self.get_error() {
+      None => return Ok(()),
+      Some(err) => err,
+    };
+
+    while let Some(err) = self.get_error() {
+      warn!("Additional OpenGL error after {}: {}", first, err);
+    }
+
+    Err(first)
+  }
+}
+
+impl Drop for GLContext {
+  fn drop(&mut self) {
+    if gl::DebugMessageCallback::is_loaded() {
+      // The callback points into `self`.
+      unsafe {
+        gl::Disable(gl::DEBUG_OUTPUT);
+        gl::DebugMessageCallback(debug_callback, ptr::null_mut());
+      }
+    }
+
+    CONTEXT_EXISTS.with(|exists| exists.set(false));
+  }
+}
+
+fn debug_source_name(source: GLenum) -> &'static str {
+  match source {
+    gl::DEBUG_SOURCE_API             => "API",
+    gl::DEBUG_SOURCE_WINDOW_SYSTEM   => "window system",
+    gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+    gl::DEBUG_SOURCE_THIRD_PARTY     => "third party",
+    gl::DEBUG_SOURCE_APPLICATION     => "application",
+    _                                => "other",
+  }
+}
+
+fn debug_type_name(typ: GLenum) -> &'static str {
+  match typ {
+    gl::DEBUG_TYPE_ERROR               => "error",
+    gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+    gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR  => "undefined behavior",
+    gl::DEBUG_TYPE_PORTABILITY         => "portability",
+    gl::DEBUG_TYPE_PERFORMANCE         => "performance",
+    gl::DEBUG_TYPE_MARKER              => "marker",
+    gl::DEBUG_TYPE_PUSH_GROUP          => "push group",
+    gl::DEBUG_TYPE_POP_GROUP           => "pop group",
+    _                                  => "other",
+  }
+}
+
+extern "system" fn debug_callback(
+  source: GLenum,
+  typ: GLenum,
+  id: GLuint,
+  severity: GLenum,
+  length: GLsizei,
+  message: *const GLchar,
+  ignored_ids: *mut c_void,
+) {
+  if !ignored_ids.is_null() {
+    let ignored_ids = unsafe { &*(ignored_ids as *const HashSet<GLuint>) };
+    if ignored_ids.contains(&id) {
+      return;
+    }
+  }
+
+  // Some drivers pass a negative length for NUL-terminated messages.
+  let message = unsafe {
+    if length < 0 {
+      CStr::from_ptr(message).to_bytes()
+    } else {
+      std::slice::from_raw_parts(message as *const u8, length as usize)
+    }
+  };
+  let message = String::from_utf8_lossy(message);
+
+  let source = debug_source_name(source);
+  let typ_name = debug_type_name(typ);
+
+  if typ == gl::DEBUG_TYPE_ERROR {
+    error!("OpenGL {} {} 0x{:x}: {}", source, typ_name, id, message);
+    return;
+  }
+
+  match severity {
+    gl::DEBUG_SEVERITY_HIGH   => error!("OpenGL {} {} 0x{:x}: {}", source, typ_name, id, message),
+    gl::DEBUG_SEVERITY_MEDIUM => warn!("OpenGL {} {} 0x{:x}: {}", source, typ_name, id, message),
+    gl::DEBUG_SEVERITY_LOW    => info!("OpenGL {} {} 0x{:x}: {}", source, typ_name, id, message),
+    _                         => debug!("OpenGL {} {} 0x{:x}: {}", source, typ_name, id, message),
+  }
 }
@@ -0,0 +1,148 @@
+use gl;
+use gl::types::*;
+use gl_context::{GLContext, GlError};
+use std::marker::PhantomData;
+use std::mem;
+
+use vertex_buffer::GLByteBuffer;
+
+/// A type that can index vertices: `u8`, `u16` or `u32`.
+pub unsafe trait Index: Copy {
+  fn gl_enum() -> GLenum;
+}
+
+unsafe impl Index for u8 {
+  fn gl_enum() -> GLenum {
+    gl::UNSIGNED_BYTE
+  }
+}
+
+unsafe impl Index for u16 {
+  fn gl_enum() -> GLenum {
+    gl::UNSIGNED_SHORT
+  }
+}
+
+unsafe impl Index for u32 {
+  fn gl_enum() -> GLenum {
+    gl::UNSIGNED_INT
+  }
+}
+
+/// Fixed-size VRAM buffer of vertex indices, for indexed drawing from a `GLArray`.
+/// The `GL_ELEMENT_ARRAY_BUFFER` binding is part of vertex array state, so the buffer is attached to a `GLArray`
+/// with `GLArray::attach_indices`, and bound and updated through `GL_COPY_WRITE_BUFFER` instead.
+/// That way, updating it never replaces the indices of whichever vertex array happens to be bound.
+pub struct IndexBuffer<'a, I> {
+  pub byte_buffer: GLByteBuffer<'a>,
+  /// length in `I`s.
+  pub length: usize,
+  phantom: PhantomData<Vec<I>>,
+}
+
+impl<'a, I: Index> IndexBuffer<'a, I> {
+  pub fn new<'b:'a>(
+    gl: &'a mut GLContext,
+    capacity: usize,
+  ) -> Result<IndexBuffer<'b, I>, GlError> {
+    Ok(IndexBuffer {
+      byte_buffer: GLByteBuffer::with_target(gl, gl::COPY_WRITE_BUFFER, capacity * mem::size_of::<I>())?,
+      length: 0,
+      phantom: PhantomData,
+    })
+  }
+
+  /// The most indices the buffer can hold.
+  pub fn capacity(&self) -> usize {
+    self.byte_buffer.capacity / mem::size_of::<I>()
+  }
+
+  /// Bind the buffer for `push`, `update` and `swap_remove`.
+  /// This doesn't affect any vertex array.
+  pub fn bind(&self, gl: &mut GLContext) {
+    self.byte_buffer.bind(gl);
+  }
+
+  /// Returns false and does nothing if this would exceed the capacity of the buffer.
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn push(&mut self, gl: &mut GLContext, is: &[I]) -> bool {
+    let length =
+      match pushed_length(self.length, self.capacity(), is.len()) {
+        None => return false,
+        Some(length) => length,
+      };
+    unsafe {
+      self.byte_buffer.push(gl, is.as_ptr() as *const u8, mem::size_of::<I>() * is.len());
+    }
+    self.length = length;
+    true
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn update(&mut self, gl: &mut GLContext, idx: usize, is: &[I]) {
+    unsafe {
+      self.byte_buffer.update(
+        gl,
+        mem::size_of::<I>() * idx,
+        is.as_ptr() as *const u8,
+        mem::size_of::<I>() * is.len(),
+      );
+    }
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn swap_remove(&mut self, gl: &mut GLContext, idx: usize, count: usize) {
+    let length = removed_length(self.length, idx, count);
+    self.byte_buffer.swap_remove(
+      gl,
+      mem::size_of::<I>() * idx,
+      mem::size_of::<I>() * count,
+    );
+    self.length = length;
+  }
+}
+
+/// The length after pushing `count` indices onto `length` of them, or `None` if that wouldn't fit in `capacity`.
+fn pushed_length(length: usize, capacity: usize, count: usize) -> Option<usize> {
+  match length.checked_add(count) {
+    Some(new_length) if new_length <= capacity => Some(new_length),
+    _ => None,
+  }
+}
+
+/// The length after removing the `count` indices at `idx`. Panics if they aren't all in the buffer.
+fn removed_length(length: usize, idx: usize, count: usize) -> usize {
+  assert!(idx <= length && count <= length - idx, "removing indices past the end of the buffer");
+  length - count
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{pushed_length, removed_length};
+
+  #[test]
+  fn push() {
+    assert_eq!(pushed_length(0, 6, 4), Some(4));
+    assert_eq!(pushed_length(4, 6, 2), Some(6));
+    assert_eq!(pushed_length(4, 6, 3), None);
+    assert_eq!(pushed_length(6, 6, 0), Some(6));
+    assert_eq!(pushed_length(1, usize::max_value(), usize::max_value()), None);
+  }
+
+  #[test]
+  fn remove() {
+    assert_eq!(removed_length(4, 0, 1), 3);
+    assert_eq!(removed_length(4, 3, 1), 3);
+    assert_eq!(removed_length(4, 0, 4), 0);
+    assert_eq!(removed_length(4, 4, 0), 4);
+  }
+
+  #[test]
+  #[should_panic]
+  fn remove_past_end() {
+    removed_length(4, 3, 2);
+  }
+}
@@ -1,13 +1,30 @@
 #![deny(warnings)]
 
 extern crate gl;
+#[cfg(feature = "glutin")]
+extern crate glutin;
 extern crate libc;
+#[cfg(feature = "naga")]
+extern crate naga;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "notify")]
+extern crate notify;
 extern crate num;
+#[cfg(feature = "sdl2")]
+extern crate sdl2;
+#[cfg(feature = "yaglw_macros")]
+extern crate yaglw_macros;
+
+#[cfg(feature = "yaglw_macros")]
+pub use yaglw_macros::{Vertex, include_shader, validate_shader};
 
 pub mod gl_context;
 pub mod framebuffer;
+pub mod index_buffer;
 pub mod shader;
+pub mod storage_buffer;
 pub mod texture;
+pub mod transform_feedback;
+pub mod uniform_buffer;
 pub mod vertex_buffer;
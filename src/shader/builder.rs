@@ -0,0 +1,99 @@
+use gl_context::GLContext;
+use std::path::{Path, PathBuf};
+
+use super::{ProgramHandle, Shader, ShaderHandle};
+use super::error::ShaderError;
+use super::include::{FileResolver, preprocess, preprocess_file};
+use super::options::LinkOptions;
+use super::stage::ShaderStage;
+
+enum Source {
+  File(Option<ShaderStage>, PathBuf),
+  String(ShaderStage, String),
+}
+
+/// Collects the stages of a program, from files or strings, then compiles and links them.
+/// `#include`s are expanded: relative to the including file, then through `include_dir`s.
+pub struct ShaderBuilder {
+  sources: Vec<Source>,
+  resolver: FileResolver,
+  options: LinkOptions,
+}
+
+impl ShaderBuilder {
+  pub fn new() -> ShaderBuilder {
+    ShaderBuilder {
+      sources: Vec::new(),
+      resolver: FileResolver::new(Vec::new()),
+      options: LinkOptions::new(),
+    }
+  }
+
+  /// Add a stage from a file. The stage is inferred from the file extension
+  /// (`.vert`, `.frag`, `.geom`, `.tesc`, `.tese` or `.comp`).
+  pub fn file<P: AsRef<Path>>(mut self, path: P) -> ShaderBuilder {
+    let path = path.as_ref();
+    self.sources.push(Source::File(ShaderStage::from_path(path), PathBuf::from(path)));
+    self
+  }
+
+  /// Add a stage from a file, with an explicit stage.
+  pub fn file_as<P: AsRef<Path>>(mut self, stage: ShaderStage, path: P) -> ShaderBuilder {
+    self.sources.push(Source::File(Some(stage), PathBuf::from(path.as_ref())));
+    self
+  }
+
+  pub fn source(mut self, stage: ShaderStage, source: &str) -> ShaderBuilder {
+    self.sources.push(Source::String(stage, String::from(source)));
+    self
+  }
+
+  pub fn include_dir<P: AsRef<Path>>(mut self, dir: P) -> ShaderBuilder {
+    self.resolver.include_dirs.push(PathBuf::from(dir.as_ref()));
+    self
+  }
+
+  /// Settings to apply before linking, e.g. attribute locations.
+  pub fn link_options(mut self, options: LinkOptions) -> ShaderBuilder {
+    self.options = options;
+    self
+  }
+
+  /// The stages added so far. Fails if a file's stage can't be inferred.
+  pub fn stages(&self) -> Result<Vec<ShaderStage>, ShaderError> {
+    self.sources.iter().map(|source| {
+      match *source {
+        Source::String(stage, _) => Ok(stage),
+        Source::File(Some(stage), _) => Ok(stage),
+        Source::File(None, ref path) =>
+          Err(ShaderError::message(
+            None,
+            format!("can't tell the shader stage of {} from its extension", path.display()),
+          )),
+      }
+    }).collect()
+  }
+
+  /// Check that the stages can form a linkable program, then compile and link them.
+  pub fn build<'a, 'b:'a>(&self, gl: &'a GLContext) -> Result<Shader<'b>, ShaderError> {
+    let stages = self.stages()?;
+    ShaderStage::validate_combination(&stages)
+      .map_err(|message| ShaderError::message(None, message))?;
+
+    let mut components = Vec::new();
+    for (source, &stage) in self.sources.iter().zip(stages.iter()) {
+      let preprocessed =
+        match *source {
+          Source::File(_, ref path) => preprocess_file(&self.resolver, &path.to_string_lossy()),
+          Source::String(_, ref source) =>
+            preprocess(&self.resolver, &format!("<{} shader>", stage), source),
+        };
+      let preprocessed = preprocessed.map_err(|err| err.into_shader_error(stage))?;
+      components.push(ShaderHandle::compile_preprocessed(gl, &preprocessed, stage)?);
+    }
+
+    let handle = ProgramHandle::new(gl);
+    self.options.apply(handle.gl_id)?;
+    Shader::link_into(handle, components)
+  }
+}
@@ -0,0 +1,259 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::iter::repeat;
+use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use super::{ProgramHandle, Shader, ShaderHandle};
+use super::error::ShaderError;
+use super::stage::ShaderStage;
+
+/// Bump this whenever the layout of cache entries changes.
+const FORMAT_VERSION: u32 = 2;
+const MAGIC: &'static [u8; 8] = b"YAGLWPB\0";
+
+/// Caches linked program binaries on disk, keyed by their sources and the driver that built them.
+///
+/// Entries are `glGetProgramBinary` output behind a header, which holds the entry's full key (driver and sources).
+/// Entries with the wrong header, or that the driver refuses to load (e.g. after a driver update),
+/// are discarded and rebuilt.
+pub struct ProgramCache {
+  pub dir: PathBuf,
+}
+
+struct Entry {
+  key: Vec<u8>,
+  format: GLenum,
+  binary: Vec<u8>,
+}
+
+fn write_u32<W: Write>(w: &mut W, x: u32) -> io::Result<()> {
+  w.write_all(&[x as u8, (x >> 8) as u8, (x >> 16) as u8, (x >> 24) as u8])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+  let mut buf = [0; 4];
+  r.read_exact(&mut buf)?;
+  Ok((buf[0] as u32) | (buf[1] as u32) << 8 | (buf[2] as u32) << 16 | (buf[3] as u32) << 24)
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+  let len = read_u32(r)? as usize;
+  let mut buf: Vec<u8> = repeat(0).take(len).collect();
+  r.read_exact(&mut buf)?;
+  Ok(buf)
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, it's the same on every Rust release, so entry names stay put.
+fn fnv1a(bytes: &[u8]) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for &b in bytes {
+    hash ^= b as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash
+}
+
+fn invalid(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Entry {
+  fn read(path: &Path) -> io::Result<Entry> {
+    let mut file = io::BufReader::new(fs::File::open(path)?);
+
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+      return Err(invalid("not a program cache entry"));
+    }
+    if read_u32(&mut file)? != FORMAT_VERSION {
+      return Err(invalid("old cache format"));
+    }
+
+    let key = read_bytes(&mut file)?;
+    let format = read_u32(&mut file)?;
+    let binary = read_bytes(&mut file)?;
+
+    Ok(Entry {
+      key: key,
+      format: format,
+      binary: binary,
+    })
+  }
+
+  fn write(&self, path: &Path) -> io::Result<()> {
+    // Write to a temporary file and rename it, so a crash never leaves a truncated entry behind.
+    let tmp = path.with_extension("tmp");
+    {
+      let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+      file.write_all(MAGIC)?;
+      write_u32(&mut file, FORMAT_VERSION)?;
+      write_u32(&mut file, self.key.len() as u32)?;
+      file.write_all(&self.key)?;
+      write_u32(&mut file, self.format)?;
+      write_u32(&mut file, self.binary.len() as u32)?;
+      file.write_all(&self.binary)?;
+      file.flush()?;
+    }
+    fs::rename(&tmp, path)
+  }
+}
+
+impl ProgramCache {
+  /// The directory is created when the first entry is stored.
+  pub fn new<P: AsRef<Path>>(dir: P) -> ProgramCache {
+    ProgramCache {
+      dir: PathBuf::from(dir.as_ref()),
+    }
+  }
+
+  /// Whether the driver can hand out program binaries at all.
+  pub fn is_supported(_gl: &GLContext) -> bool {
+    if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+      return false;
+    }
+    let mut formats = 0;
+    unsafe {
+      gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+    }
+    formats > 0
+  }
+
+  fn driver(gl: &GLContext) -> String {
+    format!(
+      "{}\n{}\n{}",
+      gl.get_string(gl::VENDOR),
+      gl.get_string(gl::RENDERER),
+      gl.get_string(gl::VERSION),
+    )
+  }
+
+  /// Everything that identifies an entry: the driver, and each stage and its source.
+  fn key(gl: &GLContext, components: &[(ShaderStage, String)]) -> Vec<u8> {
+    let mut key = Vec::new();
+    // Writing to a `Vec` can't fail.
+    let _ = ProgramCache::write_key(&mut key, &ProgramCache::driver(gl), components);
+    key
+  }
+
+  fn write_key(key: &mut Vec<u8>, driver: &str, components: &[(ShaderStage, String)]) -> io::Result<()> {
+    write_u32(key, driver.len() as u32)?;
+    key.write_all(driver.as_bytes())?;
+    for &(stage, ref source) in components {
+      write_u32(key, stage.gl_enum())?;
+      write_u32(key, source.len() as u32)?;
+      key.write_all(source.as_bytes())?;
+    }
+    Ok(())
+  }
+
+  fn entry_path(&self, key: &[u8]) -> PathBuf {
+    self.dir.join(format!("{:016x}.bin", fnv1a(key)))
+  }
+
+  /// Load the program for these stages from the cache, or compile and link it and store the result.
+  /// Cache problems are logged, and never fail the build.
+  ///
+  /// Programs loaded from a binary have no `components`.
+  pub fn build<'a, 'b:'a>(
+    &self,
+    gl: &'a GLContext,
+    components: &[(ShaderStage, String)],
+  ) -> Result<Shader<'b>, ShaderError> {
+    if !ProgramCache::is_supported(gl) {
+      return Shader::new(gl, components.iter().cloned());
+    }
+
+    let key = ProgramCache::key(gl, components);
+    let path = self.entry_path(&key);
+
+    match Entry::read(&path) {
+      Ok(ref entry) if entry.key == key => {
+        if let Some(shader) = ProgramCache::load(gl, entry) {
+          return Ok(shader);
+        }
+        info!("Driver rejected cached program binary {}; rebuilding", path.display());
+      },
+      Ok(_) => info!("Cached program binary {} is for other sources; rebuilding", path.display()),
+      Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
+      Err(err) => info!("Discarding cached program binary {}: {}", path.display(), err),
+    }
+
+    let shader = ProgramCache::compile(gl, components)?;
+    if let Err(err) = self.store(&path, key, &shader) {
+      warn!("Couldn't cache program binary {}: {}", path.display(), err);
+    }
+    Ok(shader)
+  }
+
+  fn load<'a, 'b:'a>(gl: &'a GLContext, entry: &Entry) -> Option<Shader<'b>> {
+    let handle = ProgramHandle::new(gl);
+    let mut status = gl::FALSE as GLint;
+    unsafe {
+      gl::ProgramBinary(
+        handle.gl_id,
+        entry.format,
+        entry.binary.as_ptr() as *const c_void,
+        entry.binary.len() as GLsizei,
+      );
+      gl::GetProgramiv(handle.gl_id, gl::LINK_STATUS, &mut status);
+    }
+
+    if status != (gl::TRUE as GLint) {
+      return None;
+    }
+
+    Some(Shader::from_linked(handle, Vec::new()))
+  }
+
+  fn compile<'a, 'b:'a>(
+    gl: &'a GLContext,
+    components: &[(ShaderStage, String)],
+  ) -> Result<Shader<'b>, ShaderError> {
+    let mut handles = Vec::new();
+    for &(stage, ref source) in components {
+      handles.push(ShaderHandle::compile_from(gl, source.clone(), stage)?);
+    }
+
+    let handle = ProgramHandle::new(gl);
+    unsafe {
+      gl::ProgramParameteri(handle.gl_id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+    }
+    Shader::link_into(handle, handles)
+  }
+
+  fn store(&self, path: &Path, key: Vec<u8>, shader: &Shader) -> io::Result<()> {
+    let mut len = 0;
+    unsafe {
+      gl::GetProgramiv(shader.handle.gl_id, gl::PROGRAM_BINARY_LENGTH, &mut len);
+    }
+    if len <= 0 {
+      return Err(io::Error::new(io::ErrorKind::Other, "driver returned an empty program binary"));
+    }
+
+    let mut binary: Vec<u8> = repeat(0).take(len as usize).collect();
+    let mut format = 0;
+    unsafe {
+      gl::GetProgramBinary(
+        shader.handle.gl_id,
+        len,
+        ptr::null_mut(),
+        &mut format,
+        binary.as_mut_ptr() as *mut c_void,
+      );
+    }
+
+    fs::create_dir_all(&self.dir)?;
+    Entry {
+      key: key,
+      format: format,
+      binary: binary,
+    }.write(path)
+  }
+}
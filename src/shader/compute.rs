@@ -0,0 +1,91 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std::mem;
+use vertex_buffer::GLByteBuffer;
+
+use super::{Shader, ShaderHandle};
+use super::error::ShaderError;
+use super::stage::ShaderStage;
+
+/// The layout of one indirect dispatch command, as read by `ComputeProgram::dispatch_indirect`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DispatchIndirectCommand {
+  pub num_groups_x: GLuint,
+  pub num_groups_y: GLuint,
+  pub num_groups_z: GLuint,
+}
+
+/// A program made of a single compute shader.
+pub struct ComputeProgram<'a> {
+  pub shader: Shader<'a>,
+  /// The `local_size_{x,y,z}` the shader declares.
+  pub local_size: [u32; 3],
+}
+
+impl<'a> ComputeProgram<'a> {
+  pub fn new<'b:'a>(
+    gl: &'a GLContext,
+    source: String,
+  ) -> Result<ComputeProgram<'b>, ShaderError> {
+    let component = ShaderHandle::compile_from(gl, source, ShaderStage::Compute)?;
+    let shader = Shader::link(gl, vec!(component))?;
+
+    let mut local_size = [0 as GLint; 3];
+    unsafe {
+      gl::GetProgramiv(shader.handle.gl_id, gl::COMPUTE_WORK_GROUP_SIZE, local_size.as_mut_ptr());
+    }
+
+    Ok(ComputeProgram {
+      shader: shader,
+      local_size: [local_size[0] as u32, local_size[1] as u32, local_size[2] as u32],
+    })
+  }
+
+  pub fn use_program(&self, gl: &mut GLContext) {
+    self.shader.use_shader(gl);
+  }
+
+  /// How many work groups it takes to cover `items` invocations in each dimension.
+  pub fn work_groups_for(&self, items: [u32; 3]) -> [u32; 3] {
+    let mut groups = [0; 3];
+    for i in 0..3 {
+      // Rounds up without overflowing, unlike `(items + size - 1) / size`.
+      groups[i] = items[i] / self.local_size[i] + (items[i] % self.local_size[i] != 0) as u32;
+    }
+    groups
+  }
+
+  /// Launch `x * y * z` work groups.
+  /// N.B. For performance reasons, this does NOT use the program.
+  /// It will do the wrong thing if `use_program` has not been correctly called.
+  pub fn dispatch(&self, _gl: &mut GLContext, x: u32, y: u32, z: u32) {
+    unsafe {
+      gl::DispatchCompute(x, y, z);
+    }
+  }
+
+  /// Launch enough work groups to cover `items` invocations in each dimension.
+  /// The shader is responsible for ignoring invocations past the end.
+  /// N.B. For performance reasons, this does NOT use the program.
+  /// It will do the wrong thing if `use_program` has not been correctly called.
+  pub fn dispatch_for(&self, gl: &mut GLContext, items: [u32; 3]) {
+    let groups = self.work_groups_for(items);
+    self.dispatch(gl, groups[0], groups[1], groups[2]);
+  }
+
+  /// Launch work groups with counts read from the `DispatchIndirectCommand` at byte `offset` in `buffer`.
+  /// This binds `buffer` to gl::DISPATCH_INDIRECT_BUFFER.
+  /// N.B. For performance reasons, this does NOT use the program.
+  /// It will do the wrong thing if `use_program` has not been correctly called.
+  pub fn dispatch_indirect(&self, _gl: &mut GLContext, buffer: &GLByteBuffer, offset: usize) {
+    assert!(offset % 4 == 0, "dispatch_indirect offset must be 4-byte aligned");
+    assert!(offset + mem::size_of::<DispatchIndirectCommand>() <= buffer.length);
+
+    unsafe {
+      gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, buffer.handle.gl_id);
+      gl::DispatchComputeIndirect(offset as GLintptr);
+    }
+  }
+}
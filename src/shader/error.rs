@@ -0,0 +1,321 @@
+use std;
+use std::fmt;
+
+use super::stage::ShaderStage;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+  Error,
+  Warning,
+  Note,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Severity::Error   => write!(f, "error"),
+      Severity::Warning => write!(f, "warning"),
+      Severity::Note    => write!(f, "note"),
+    }
+  }
+}
+
+/// One message from a shader or program info log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  /// GLSL source string number, as it appears in the log.
+  pub file: Option<u32>,
+  /// The name of the file `file` refers to, when it's known (e.g. after `#include` expansion).
+  pub file_name: Option<String>,
+  /// 1-based line number.
+  pub line: Option<u32>,
+  /// Only reported by some drivers (e.g. Mesa).
+  pub column: Option<u32>,
+  pub message: String,
+  /// The source line this diagnostic points at, if it could be found.
+  pub excerpt: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match (&self.file_name, self.file) {
+      (&Some(ref name), _) => write!(f, "{}:", name)?,
+      (&None, Some(file)) => write!(f, "{}:", file)?,
+      (&None, None) => {},
+    }
+    if let Some(line) = self.line {
+      write!(f, "{}:", line)?;
+    }
+    if let Some(column) = self.column {
+      write!(f, "{}:", column)?;
+    }
+    if self.file_name.is_some() || self.file.is_some() || self.line.is_some() {
+      write!(f, " ")?;
+    }
+    write!(f, "{}: {}", self.severity, self.message)?;
+    if let Some(ref excerpt) = self.excerpt {
+      write!(f, "\n    | {}", excerpt)?;
+    }
+    Ok(())
+  }
+}
+
+/// A shader stage failed to compile, or a program failed to link.
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+  /// The stage that failed to compile, or `None` if linking failed.
+  pub stage: Option<ShaderStage>,
+  /// The info log, exactly as the driver returned it.
+  pub log: String,
+  /// `log`, parsed line by line. Lines that couldn't be parsed are left out.
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ShaderError {
+  pub fn compile(stage: ShaderStage, log: String, source: &str) -> ShaderError {
+    let mut diagnostics = parse_log(&log);
+    for diagnostic in &mut diagnostics {
+      diagnostic.excerpt = excerpt(source, diagnostic);
+    }
+
+    ShaderError {
+      stage: Some(stage),
+      log: log,
+      diagnostics: diagnostics,
+    }
+  }
+
+  pub fn link(log: String) -> ShaderError {
+    ShaderError {
+      stage: None,
+      diagnostics: parse_log(&log),
+      log: log,
+    }
+  }
+
+  /// An error that didn't come from the driver, e.g. a bad combination of stages.
+  pub fn message(stage: Option<ShaderStage>, message: String) -> ShaderError {
+    ShaderError {
+      stage: stage,
+      log: message,
+      diagnostics: Vec::new(),
+    }
+  }
+}
+
+impl fmt::Display for ShaderError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.stage {
+      Some(stage) => write!(f, "error compiling {} shader:", stage)?,
+      None => write!(f, "error linking shader program:")?,
+    }
+
+    if self.diagnostics.is_empty() {
+      return write!(f, "\n{}", self.log.trim_end());
+    }
+
+    for diagnostic in &self.diagnostics {
+      write!(f, "\n{}", diagnostic)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ShaderError {}
+
+fn excerpt(source: &str, diagnostic: &Diagnostic) -> Option<String> {
+  // Only one source string is passed to `glShaderSource`.
+  if diagnostic.file.unwrap_or(0) != 0 {
+    return None;
+  }
+
+  diagnostic.line.and_then(|line| source_line(source, line))
+}
+
+/// Fetch a 1-based line from `source`.
+pub fn source_line(source: &str, line: u32) -> Option<String> {
+  if line == 0 {
+    return None;
+  }
+  source.lines().nth(line as usize - 1).map(|l| String::from(l.trim_end()))
+}
+
+/// Parses a shader or program info log. Understands the formats:
+///
+///   NVIDIA:      `0(12) : error C0000: message`
+///   Mesa:        `0:12(5): error: message`
+///   AMD/Apple:   `ERROR: 0:12: message`
+pub fn parse_log(log: &str) -> Vec<Diagnostic> {
+  log.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Diagnostic> {
+  let line = line.trim();
+  if line.is_empty() {
+    return None;
+  }
+
+  // AMD and Apple put the severity before the location.
+  for &(prefix, severity) in &[("ERROR:", Severity::Error), ("WARNING:", Severity::Warning)] {
+    if line.starts_with(prefix) {
+      let rest = line[prefix.len()..].trim_start();
+      let location =
+        take_number(rest)
+          .and_then(|(file, rest)| take_char(rest, ':').map(|rest| (file, rest)))
+          .and_then(|(file, rest)| take_number(rest).map(|(line, rest)| (file, line, rest)))
+          .and_then(|(file, line, rest)| take_char(rest, ':').map(|rest| (file, line, rest)));
+      return Some(match location {
+        Some((file, line, message)) =>
+          Diagnostic {
+            severity: severity,
+            file: Some(file),
+            file_name: None,
+            line: Some(line),
+            column: None,
+            message: String::from(message.trim()),
+            excerpt: None,
+          },
+        None =>
+          Diagnostic {
+            severity: severity,
+            file: None,
+            file_name: None,
+            line: None,
+            column: None,
+            message: String::from(rest),
+            excerpt: None,
+          },
+      });
+    }
+  }
+
+  let (file, rest) = match take_number(line) {
+    None => return None,
+    Some(r) => r,
+  };
+
+  let (line_number, column, rest) =
+    if let Some(rest) = take_char(rest, '(') {
+      // NVIDIA: `0(12)`
+      let (line_number, rest) = match take_number(rest) { None => return None, Some(r) => r };
+      let rest = match take_char(rest, ')') { None => return None, Some(r) => r };
+      (line_number, None, rest)
+    } else if let Some(rest) = take_char(rest, ':') {
+      // Mesa: `0:12(5)`
+      let (line_number, rest) = match take_number(rest) { None => return None, Some(r) => r };
+      match take_char(rest, '(') {
+        None => (line_number, None, rest),
+        Some(rest) => {
+          let (column, rest) = match take_number(rest) { None => return None, Some(r) => r };
+          let rest = match take_char(rest, ')') { None => return None, Some(r) => r };
+          (line_number, Some(column), rest)
+        },
+      }
+    } else {
+      return None;
+    };
+
+  let rest = match take_char(rest.trim_start(), ':') {
+    None => return None,
+    Some(rest) => rest.trim_start(),
+  };
+
+  // `error C0000: message`, `error: message`, `preprocessor error: message`
+  let (severity, message) =
+    match rest.find(':') {
+      None => (Severity::Error, rest),
+      Some(i) => {
+        let head = rest[..i].to_lowercase();
+        let severity =
+          if head.contains("error") {
+            Severity::Error
+          } else if head.contains("warning") {
+            Severity::Warning
+          } else {
+            Severity::Note
+          };
+        (severity, &rest[i + 1..])
+      },
+    };
+
+  Some(Diagnostic {
+    severity: severity,
+    file: Some(file),
+    file_name: None,
+    line: Some(line_number),
+    column: column,
+    message: String::from(message.trim()),
+    excerpt: None,
+  })
+}
+
+fn take_number(s: &str) -> Option<(u32, &str)> {
+  let end = s.find(|c: char| !c.is_digit(10)).unwrap_or(s.len());
+  if end == 0 {
+    return None;
+  }
+  s[..end].parse().ok().map(|n| (n, &s[end..]))
+}
+
+fn take_char(s: &str, c: char) -> Option<&str> {
+  if s.starts_with(c) {
+    Some(&s[c.len_utf8()..])
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn diagnostic(severity: Severity, file: u32, line: u32, column: Option<u32>, message: &str) -> Diagnostic {
+    Diagnostic {
+      severity: severity,
+      file: Some(file),
+      file_name: None,
+      line: Some(line),
+      column: column,
+      message: String::from(message),
+      excerpt: None,
+    }
+  }
+
+  #[test]
+  fn nvidia() {
+    assert_eq!(
+      parse_line(r#"0(12) : error C1008: undefined variable "colour""#),
+      Some(diagnostic(Severity::Error, 0, 12, None, r#"undefined variable "colour""#)),
+    );
+    assert_eq!(
+      parse_line("0(3) : warning C7050: \"v\" might be used before being initialized"),
+      Some(diagnostic(Severity::Warning, 0, 3, None, "\"v\" might be used before being initialized")),
+    );
+  }
+
+  #[test]
+  fn mesa() {
+    assert_eq!(
+      parse_line("0:12(5): error: `colour' undeclared"),
+      Some(diagnostic(Severity::Error, 0, 12, Some(5), "`colour' undeclared")),
+    );
+  }
+
+  #[test]
+  fn amd() {
+    assert_eq!(
+      parse_line("ERROR: 0:12: 'colour' : undeclared identifier"),
+      Some(diagnostic(Severity::Error, 0, 12, None, "'colour' : undeclared identifier")),
+    );
+  }
+
+  #[test]
+  fn unparseable() {
+    assert_eq!(parse_line("Vertex shader failed to compile with the following errors:"), None);
+    assert_eq!(
+      parse_log("Fragment info\n-------------\n0(7) : error C0000: syntax error\n"),
+      vec!(diagnostic(Severity::Error, 0, 7, None, "syntax error")),
+    );
+  }
+}
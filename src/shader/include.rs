@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::error::{Diagnostic, Severity, ShaderError, source_line};
+use super::stage::ShaderStage;
+
+/// Finds the files named by `#include` directives.
+pub trait IncludeResolver {
+  /// Look up `#include "path"`, which appears in the file named `includer`.
+  /// `includer` is empty when resolving a root file.
+  /// Returns the file's canonical name, which is used to detect include cycles, and its contents.
+  fn resolve(&self, path: &str, includer: &str) -> io::Result<(String, String)>;
+}
+
+/// Resolves includes from a fixed set of named sources.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+  pub files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+  pub fn new() -> MemoryResolver {
+    MemoryResolver {
+      files: HashMap::new(),
+    }
+  }
+
+  pub fn insert(&mut self, name: &str, source: &str) {
+    self.files.insert(String::from(name), String::from(source));
+  }
+}
+
+impl IncludeResolver for MemoryResolver {
+  fn resolve(&self, path: &str, _includer: &str) -> io::Result<(String, String)> {
+    match self.files.get(path) {
+      None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no shader source named {:?}", path))),
+      Some(source) => Ok((String::from(path), source.clone())),
+    }
+  }
+}
+
+/// Resolves includes relative to the including file, then relative to each of `include_dirs`.
+#[derive(Debug, Clone, Default)]
+pub struct FileResolver {
+  pub include_dirs: Vec<PathBuf>,
+}
+
+impl FileResolver {
+  pub fn new(include_dirs: Vec<PathBuf>) -> FileResolver {
+    FileResolver {
+      include_dirs: include_dirs,
+    }
+  }
+
+  /// Where `#include "path"` in `includer` is looked for, in order.
+  pub fn candidates(&self, path: &str, includer: &str) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    match Path::new(includer).parent() {
+      Some(dir) if !includer.is_empty() => candidates.push(dir.join(path)),
+      _ => candidates.push(PathBuf::from(path)),
+    }
+    for dir in &self.include_dirs {
+      candidates.push(dir.join(path));
+    }
+    candidates
+  }
+}
+
+impl IncludeResolver for FileResolver {
+  fn resolve(&self, path: &str, includer: &str) -> io::Result<(String, String)> {
+    for candidate in self.candidates(path, includer) {
+      if !candidate.is_file() {
+        continue;
+      }
+
+      let canonical = fs::canonicalize(&candidate)?;
+      let mut source = String::new();
+      fs::File::open(&canonical)?.read_to_string(&mut source)?;
+      return Ok((canonical.to_string_lossy().into_owned(), source));
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("couldn't find {:?}", path)))
+  }
+}
+
+/// One of the files that went into a `Preprocessed` source.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+  pub name: String,
+  pub contents: String,
+}
+
+/// GLSL source with every `#include` expanded.
+/// `#line` directives in `source` refer to indices into `files`; the root file is index 0.
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+  pub source: String,
+  pub files: Vec<SourceFile>,
+}
+
+impl Preprocessed {
+  /// Point the diagnostics in a compile error for `self.source` back at the original files.
+  pub fn remap(&self, err: &mut ShaderError) {
+    for diagnostic in &mut err.diagnostics {
+      let file = match diagnostic.file.and_then(|i| self.files.get(i as usize)) {
+        None => continue,
+        Some(file) => file,
+      };
+      diagnostic.file_name = Some(file.name.clone());
+      diagnostic.excerpt = diagnostic.line.and_then(|line| source_line(&file.contents, line));
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum IncludeError {
+  /// The resolver couldn't produce a file.
+  Resolve {
+    path: String,
+    includer: String,
+    line: u32,
+    error: io::Error,
+  },
+  /// A file (transitively) includes itself. Lists the chain of includes, ending in the repeated file.
+  Cycle(Vec<String>),
+  /// Couldn't parse an `#include` line.
+  Malformed {
+    file: String,
+    line: u32,
+  },
+}
+
+impl fmt::Display for IncludeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      IncludeError::Resolve { ref path, ref includer, line, ref error } =>
+        write!(f, "{}:{}: couldn't include {:?}: {}", includer, line, path, error),
+      IncludeError::Cycle(ref chain) =>
+        write!(f, "#include cycle: {}", chain.join(" -> ")),
+      IncludeError::Malformed { ref file, line } =>
+        write!(f, "{}:{}: malformed #include", file, line),
+    }
+  }
+}
+
+impl IncludeError {
+  /// Report this as an error compiling `stage`.
+  pub fn into_shader_error(self, stage: ShaderStage) -> ShaderError {
+    let (file_name, line) =
+      match self {
+        IncludeError::Resolve { ref includer, .. } if includer.is_empty() => (None, None),
+        IncludeError::Resolve { ref includer, line, .. } => (Some(includer.clone()), Some(line)),
+        IncludeError::Malformed { ref file, line } => (Some(file.clone()), Some(line)),
+        IncludeError::Cycle(_) => (None, None),
+      };
+    let message = format!("{}", self);
+
+    ShaderError {
+      stage: Some(stage),
+      diagnostics: vec!(
+        Diagnostic {
+          severity: Severity::Error,
+          file: None,
+          file_name: file_name,
+          line: line,
+          column: None,
+          message: message.clone(),
+          excerpt: None,
+        },
+      ),
+      log: message,
+    }
+  }
+}
+
+/// Preprocess the file named `path`, which is found through `resolver`.
+pub fn preprocess_file<R: IncludeResolver + ?Sized>(
+  resolver: &R,
+  path: &str,
+) -> Result<Preprocessed, IncludeError> {
+  let (name, source) =
+    resolver.resolve(path, "").map_err(|error| {
+      IncludeError::Resolve {
+        path: String::from(path),
+        includer: String::new(),
+        line: 0,
+        error: error,
+      }
+    })?;
+  preprocess(resolver, &name, &source)
+}
+
+/// Expand the `#include "..."` directives in `source` (named `name`).
+/// Files containing `#pragma once` are only expanded the first time they're included.
+pub fn preprocess<R: IncludeResolver + ?Sized>(
+  resolver: &R,
+  name: &str,
+  source: &str,
+) -> Result<Preprocessed, IncludeError> {
+  let mut state = State {
+    resolver: resolver,
+    output: String::new(),
+    files: Vec::new(),
+    file_ids: HashMap::new(),
+    once: HashSet::new(),
+    stack: Vec::new(),
+    line_offset: line_directive_offset(source),
+  };
+  state.expand(name, source)?;
+
+  Ok(Preprocessed {
+    source: state.output,
+    files: state.files,
+  })
+}
+
+struct State<'a, R: IncludeResolver + ?Sized + 'a> {
+  resolver: &'a R,
+  output: String,
+  files: Vec<SourceFile>,
+  file_ids: HashMap<String, u32>,
+  once: HashSet<String>,
+  stack: Vec<String>,
+  line_offset: u32,
+}
+
+impl<'a, R: IncludeResolver + ?Sized> State<'a, R> {
+  fn file_id(&mut self, name: &str, contents: &str) -> u32 {
+    if let Some(&id) = self.file_ids.get(name) {
+      return id;
+    }
+    let id = self.files.len() as u32;
+    self.files.push(SourceFile {
+      name: String::from(name),
+      contents: String::from(contents),
+    });
+    self.file_ids.insert(String::from(name), id);
+    id
+  }
+
+  fn line_directive(&mut self, line: u32, file: u32) {
+    self.output.push_str(&format!("#line {} {}\n", line - self.line_offset, file));
+  }
+
+  fn expand(&mut self, name: &str, source: &str) -> Result<(), IncludeError> {
+    if self.once.contains(name) {
+      return Ok(());
+    }
+    if self.stack.iter().any(|s| s == name) {
+      let mut chain = self.stack.clone();
+      chain.push(String::from(name));
+      return Err(IncludeError::Cycle(chain));
+    }
+
+    let is_root = self.stack.is_empty();
+    let id = self.file_id(name, source);
+    self.stack.push(String::from(name));
+
+    // The root file's `#version` has to stay the first thing in the output,
+    // so it gets its `#line` directive after that.
+    if !is_root {
+      self.line_directive(1, id);
+    }
+
+    for (i, line) in source.lines().enumerate() {
+      let line_number = i as u32 + 1;
+      match directive(line) {
+        Some(("include", args)) => {
+          let path = match include_path(args) {
+            None => return Err(IncludeError::Malformed { file: String::from(name), line: line_number }),
+            Some(path) => path,
+          };
+          let (child_name, child_source) =
+            self.resolver.resolve(path, name).map_err(|error| {
+              IncludeError::Resolve {
+                path: String::from(path),
+                includer: String::from(name),
+                line: line_number,
+                error: error,
+              }
+            })?;
+          self.expand(&child_name, &child_source)?;
+          self.line_directive(line_number + 1, id);
+        },
+        Some(("pragma", args)) if args.trim() == "once" => {
+          self.once.insert(String::from(name));
+          self.output.push('\n');
+        },
+        Some(("version", _)) => {
+          if is_root {
+            self.output.push_str(line);
+            self.output.push('\n');
+            self.line_directive(line_number + 1, id);
+          } else {
+            // Only the root file gets to declare a version.
+            self.output.push('\n');
+          }
+        },
+        _ => {
+          self.output.push_str(line);
+          self.output.push('\n');
+        },
+      }
+    }
+
+    self.stack.pop();
+    Ok(())
+  }
+}
+
+/// Splits a preprocessor line like `  #  include "foo"` into `("include", " \"foo\"")`.
+pub fn directive(line: &str) -> Option<(&str, &str)> {
+  let line = line.trim_start();
+  if !line.starts_with('#') {
+    return None;
+  }
+  let line = line[1..].trim_start();
+  let end = line.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(line.len());
+  Some((&line[..end], &line[end..]))
+}
+
+/// Parses `"path"` or `<path>`.
+fn include_path(args: &str) -> Option<&str> {
+  let args = args.trim();
+  let close = match args.chars().next() {
+    Some('"') => '"',
+    Some('<') => '>',
+    _ => return None,
+  };
+  let args = &args[1..];
+  args.find(close).map(|end| &args[..end])
+}
+
+/// Before GLSL 3.30 (and GLSL ES 3.00), the line after `#line n` was numbered `n + 1`.
+/// Returns how much to subtract from line numbers in `#line` directives to compensate.
+pub fn line_directive_offset(source: &str) -> u32 {
+  let version =
+    source
+      .lines()
+      .filter_map(directive)
+      .find(|&(name, _)| name == "version")
+      .map(|(_, args)| args.split_whitespace().collect::<Vec<_>>());
+
+  match version {
+    // No `#version` means GLSL 1.10.
+    None => 1,
+    Some(ref args) if args.get(1) == Some(&"es") => 0,
+    Some(args) => {
+      match args.get(0).and_then(|v| v.parse::<u32>().ok()) {
+        Some(v) if v < 330 => 1,
+        _ => 0,
+      }
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn resolver(files: &[(&str, &str)]) -> MemoryResolver {
+    let mut resolver = MemoryResolver::new();
+    for &(name, source) in files {
+      resolver.insert(name, source);
+    }
+    resolver
+  }
+
+  #[test]
+  fn cycle() {
+    let resolver = resolver(&[("a", "#include \"b\"\n"), ("b", "#include \"a\"\n")]);
+    match preprocess_file(&resolver, "a") {
+      Err(IncludeError::Cycle(chain)) => assert_eq!(chain, ["a", "b", "a"]),
+      r => panic!("expected an include cycle, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn pragma_once() {
+    let resolver =
+      resolver(&[
+        ("main", "#include \"common\"\n#include \"common\"\n"),
+        ("common", "#pragma once\nfloat f;\n"),
+      ]);
+    let preprocessed = preprocess_file(&resolver, "main").unwrap();
+    assert_eq!(preprocessed.source.matches("float f;").count(), 1);
+  }
+
+  #[test]
+  fn line_directives() {
+    let resolver =
+      resolver(&[
+        ("main", "#version 330\n#include \"lib\"\nvoid main() {}\n"),
+        ("lib", "float f;\n"),
+      ]);
+    let preprocessed = preprocess_file(&resolver, "main").unwrap();
+    assert_eq!(
+      preprocessed.source,
+      "#version 330\n#line 2 0\n#line 1 1\nfloat f;\n#line 3 0\nvoid main() {}\n",
+    );
+    let names: Vec<&str> = preprocessed.files.iter().map(|f| &f.name[..]).collect();
+    assert_eq!(names, ["main", "lib"]);
+  }
+
+  #[test]
+  fn line_directives_before_330() {
+    // `#line n` numbers the next line `n + 1` here.
+    let resolver =
+      resolver(&[
+        ("main", "#version 120\n#include \"lib\"\nvoid main() {}\n"),
+        ("lib", "float f;\n"),
+      ]);
+    let preprocessed = preprocess_file(&resolver, "main").unwrap();
+    assert_eq!(
+      preprocessed.source,
+      "#version 120\n#line 1 0\n#line 0 1\nfloat f;\n#line 2 0\nvoid main() {}\n",
+    );
+  }
+
+  #[test]
+  fn line_offsets() {
+    assert_eq!(line_directive_offset("void main() {}\n"), 1);
+    assert_eq!(line_directive_offset("#version 120\n"), 1);
+    assert_eq!(line_directive_offset("#version 330 core\n"), 0);
+    assert_eq!(line_directive_offset("#version 300 es\n"), 0);
+  }
+}
@@ -0,0 +1,485 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::iter::repeat;
+use std::ptr;
+use std::marker::PhantomData;
+use transform_feedback::FeedbackBufferMode;
+
+mod builder;
+mod cache;
+mod compute;
+mod error;
+mod include;
+mod offline;
+mod options;
+mod pipeline;
+mod reflect;
+mod reload;
+mod stage;
+mod uniform;
+mod validate;
+mod variants;
+
+pub use self::builder::ShaderBuilder;
+pub use self::cache::ProgramCache;
+pub use self::compute::{ComputeProgram, DispatchIndirectCommand};
+pub use self::error::{Diagnostic, Severity, ShaderError, parse_log};
+pub use self::include::{
+  FileResolver,
+  IncludeError,
+  IncludeResolver,
+  MemoryResolver,
+  Preprocessed,
+  SourceFile,
+  preprocess,
+  preprocess_file,
+};
+pub use self::offline::{OfflineValidator, Validation};
+pub use self::options::LinkOptions;
+pub use self::pipeline::{InterfaceError, ProgramPipeline, SeparableProgram};
+pub use self::reflect::{
+  AttributeInfo,
+  GlslType,
+  InterfaceVariable,
+  ProgramInfo,
+  UniformBlockInfo,
+  UniformInfo,
+};
+pub use self::reload::ReloadableShader;
+pub use self::stage::ShaderStage;
+pub use self::uniform::{Uniform, UniformElement, UniformError};
+pub use self::validate::{ValidationError, ValidationReport};
+pub use self::variants::{Defines, ShaderVariants};
+
+fn shader_info_log(gl_id: GLuint) -> String {
+  let mut len = 0;
+  unsafe {
+    gl::GetShaderiv(gl_id, gl::INFO_LOG_LENGTH, &mut len);
+  }
+  if len <= 0 {
+    return String::new();
+  }
+  let mut buf: Vec<u8> = repeat(0).take(len as usize).collect();
+  unsafe {
+    gl::GetShaderInfoLog(gl_id, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+  }
+  buf.pop(); // skip the trailing null character
+  String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn program_info_log(gl_id: GLuint) -> String {
+  let mut len = 0;
+  unsafe {
+    gl::GetProgramiv(gl_id, gl::INFO_LOG_LENGTH, &mut len);
+  }
+  if len <= 0 {
+    return String::new();
+  }
+  let mut buf: Vec<u8> = repeat(0).take(len as usize).collect();
+  unsafe {
+    gl::GetProgramInfoLog(gl_id, len, ptr::null_mut(), buf.as_mut_ptr() as *mut GLchar);
+  }
+  buf.pop(); // skip the trailing null character
+  String::from_utf8_lossy(&buf).into_owned()
+}
+
+pub struct ProgramHandle<'a> {
+  pub gl_id: GLuint,
+  phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> ProgramHandle<'a> {
+  pub fn new<'b:'a>(_gl: &'a GLContext) -> ProgramHandle<'b> {
+    let gl_id = unsafe {
+      gl::CreateProgram()
+    };
+
+    assert!(gl_id != 0);
+
+    ProgramHandle {
+      gl_id: gl_id,
+      phantom: PhantomData,
+    }
+  }
+}
+
+impl<'a> Drop for ProgramHandle<'a> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteProgram(self.gl_id);
+    }
+  }
+}
+
+pub struct ShaderHandle<'a> {
+  pub gl_id: GLuint,
+  pub stage: ShaderStage,
+  phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> ShaderHandle<'a> {
+  /// With the `offline_validation` feature, `shader_source` is checked by an `OfflineValidator` first.
+  pub fn compile_from<'b:'a>(
+    _gl: &'a GLContext,
+    shader_source: String,
+    stage: ShaderStage,
+  ) -> Result<ShaderHandle<'b>, ShaderError> {
+    offline::check(stage, &shader_source)?;
+
+    let gl_id = unsafe {
+      gl::CreateShader(stage.gl_enum())
+    };
+
+    assert!(gl_id != 0);
+
+    let handle = ShaderHandle {
+      gl_id: gl_id,
+      stage: stage,
+      phantom: PhantomData,
+    };
+
+    // Attempt to compile the shader
+    {
+      // e.g. from a `#define` value with a NUL in it.
+      let c_str =
+        CString::new(shader_source.as_bytes())
+          .map_err(|_| ShaderError::message(Some(stage), String::from("shader source contains a NUL byte")))?;
+      let ptr = c_str.as_ptr() as *const i8;
+      unsafe {
+        gl::ShaderSource(gl_id, 1, &ptr, ptr::null());
+        gl::CompileShader(gl_id);
+      }
+    }
+
+    // Get the compile status
+    let mut status = gl::FALSE as GLint;
+    unsafe {
+      gl::GetShaderiv(gl_id, gl::COMPILE_STATUS, &mut status);
+    }
+
+    if status != (gl::TRUE as GLint) {
+      return Err(ShaderError::compile(stage, shader_info_log(gl_id), &shader_source));
+    }
+
+    Ok(handle)
+  }
+
+  /// Compile the output of `preprocess`, reporting errors against the original files.
+  pub fn compile_preprocessed<'b:'a>(
+    gl: &'a GLContext,
+    preprocessed: &Preprocessed,
+    stage: ShaderStage,
+  ) -> Result<ShaderHandle<'b>, ShaderError> {
+    ShaderHandle::compile_from(gl, preprocessed.source.clone(), stage)
+      .map_err(|mut err| {
+        preprocessed.remap(&mut err);
+        err
+      })
+  }
+}
+
+impl<'a> Drop for ShaderHandle<'a> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteShader(self.gl_id);
+    }
+  }
+}
+
+pub struct Shader<'a> {
+  pub handle: ProgramHandle<'a>,
+  pub components: Vec<ShaderHandle<'a>>,
+  /// Cached uniform locations, including `None` for names that weren't found.
+  pub uniforms: HashMap<String, Option<GLint>>,
+  /// The program's active uniforms, attributes and uniform blocks, queried right after linking.
+  pub info: ProgramInfo,
+}
+
+impl<'a> Shader<'a> {
+  pub fn new<'b:'a, T: Iterator<Item=(ShaderStage, String)>>(
+    gl: &'a GLContext,
+    shader_components: T,
+  ) -> Result<Shader<'b>, ShaderError> {
+    let mut components = Vec::new();
+    for (stage, content) in shader_components {
+      components.push(ShaderHandle::compile_from(gl, content, stage)?);
+    }
+
+    Shader::link(gl, components)
+  }
+
+  /// Like `new`, but expands `#include`s through `resolver` first.
+  /// Each component is (stage, file name, source).
+  pub fn with_includes<'b:'a, R, T>(
+    gl: &'a GLContext,
+    resolver: &R,
+    shader_components: T,
+  ) -> Result<Shader<'b>, ShaderError>
+    where R: IncludeResolver + ?Sized,
+          T: Iterator<Item=(ShaderStage, String, String)>,
+  {
+    let mut components = Vec::new();
+    for (stage, name, content) in shader_components {
+      let preprocessed =
+        preprocess(resolver, &name, &content)
+          .map_err(|err| err.into_shader_error(stage))?;
+      components.push(ShaderHandle::compile_preprocessed(gl, &preprocessed, stage)?);
+    }
+
+    Shader::link(gl, components)
+  }
+
+  /// Like `new`, but applies `options` (e.g. attribute locations) before linking.
+  pub fn with_options<'b:'a, T: Iterator<Item=(ShaderStage, String)>>(
+    gl: &'a GLContext,
+    shader_components: T,
+    options: &LinkOptions,
+  ) -> Result<Shader<'b>, ShaderError> {
+    let mut components = Vec::new();
+    for (stage, content) in shader_components {
+      components.push(ShaderHandle::compile_from(gl, content, stage)?);
+    }
+
+    let handle = ProgramHandle::new(gl);
+    options.apply(handle.gl_id)?;
+    Shader::link_into(handle, components)
+  }
+
+  /// Like `new`, but captures the `varyings` outputs with transform feedback.
+  /// Varyings can be named like `particle.velocity` or `positions[2]`.
+  pub fn with_transform_feedback<'b:'a, T: Iterator<Item=(ShaderStage, String)>>(
+    gl: &'a GLContext,
+    shader_components: T,
+    varyings: &[&str],
+    mode: FeedbackBufferMode,
+  ) -> Result<Shader<'b>, ShaderError> {
+    Shader::with_options(gl, shader_components, &LinkOptions::new().transform_feedback(varyings, mode))
+  }
+
+  /// Link already-compiled stages into a program.
+  /// Fails before linking if the stages can't form a complete program.
+  pub fn link<'b:'a>(
+    gl: &'a GLContext,
+    components: Vec<ShaderHandle<'b>>,
+  ) -> Result<Shader<'b>, ShaderError> {
+    let handle = ProgramHandle::new(gl);
+    Shader::link_into(handle, components)
+  }
+
+  /// Like `link`, but into a program that has already been created (and e.g. configured with `glProgramParameteri`).
+  pub(crate) fn link_into(
+    handle: ProgramHandle<'a>,
+    components: Vec<ShaderHandle<'a>>,
+  ) -> Result<Shader<'a>, ShaderError> {
+    let stages: Vec<ShaderStage> = components.iter().map(|c| c.stage).collect();
+    ShaderStage::validate_combination(&stages)
+      .map_err(|message| ShaderError::message(None, message))?;
+
+    Shader::attach_and_link(handle, components)
+  }
+
+  /// Make `handle` `GL_PROGRAM_SEPARABLE` and link `components` into it.
+  /// Separable programs can hold any subset of stages, so the stage combination isn't checked.
+  pub(crate) fn link_separable(
+    handle: ProgramHandle<'a>,
+    components: Vec<ShaderHandle<'a>>,
+  ) -> Result<Shader<'a>, ShaderError> {
+    unsafe {
+      gl::ProgramParameteri(handle.gl_id, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+    }
+
+    Shader::attach_and_link(handle, components)
+  }
+
+  fn attach_and_link(
+    handle: ProgramHandle<'a>,
+    components: Vec<ShaderHandle<'a>>,
+  ) -> Result<Shader<'a>, ShaderError> {
+    for s in &components {
+      unsafe {
+        gl::AttachShader(handle.gl_id, s.gl_id);
+      }
+    }
+
+    unsafe {
+      gl::LinkProgram(handle.gl_id);
+    }
+
+    // Get the link status
+    let mut status = gl::FALSE as GLint;
+    unsafe {
+      gl::GetProgramiv(handle.gl_id, gl::LINK_STATUS, &mut status);
+    }
+
+    if status != (gl::TRUE as GLint) {
+      return Err(ShaderError::link(program_info_log(handle.gl_id)));
+    }
+
+    Ok(Shader::from_linked(handle, components))
+  }
+
+  /// Wrap a program that has already been linked successfully.
+  pub fn from_linked(
+    handle: ProgramHandle<'a>,
+    components: Vec<ShaderHandle<'a>>,
+  ) -> Shader<'a> {
+    let info = ProgramInfo::query(handle.gl_id);
+
+    Shader {
+      handle: handle,
+      components: components,
+      uniforms: HashMap::new(),
+      info: info,
+    }
+  }
+
+  pub fn use_shader(&self, _gl: &mut GLContext) {
+    unsafe {
+      gl::UseProgram(self.handle.gl_id)
+    }
+  }
+
+  /// Look up a uniform by name, e.g. `time`, `lights[3].color`, or `weights[2]`.
+  /// Returns `None` if there's no such active uniform (it may have been optimized out).
+  /// Lookups are cached, including failed ones, so probing optional uniforms is cheap.
+  pub fn get_uniform_location(&mut self, name: &str) -> Option<GLint> {
+    if let Some(&loc) = self.uniforms.get(name) {
+      return loc;
+    }
+
+    // GLSL names can't contain NUL.
+    let c_name =
+      match CString::new(name.as_bytes()) {
+        Ok(c_name) => c_name,
+        Err(_) => return None,
+      };
+    let loc = unsafe {
+      gl::GetUniformLocation(self.handle.gl_id, c_name.as_ptr() as *const GLchar)
+    };
+    let loc = if loc == -1 { None } else { Some(loc) };
+    self.uniforms.insert(String::from(name), loc);
+    loc
+  }
+
+  /// Set a uniform by name. `value` can be a single value, or a slice for array uniforms.
+  /// In debug builds, `value` is checked against the uniform's reflected type.
+  /// N.B. For performance reasons, this does NOT use the shader.
+  /// It will do the wrong thing if `use_shader` has not been correctly called.
+  pub fn set_uniform<U: Uniform + ?Sized>(
+    &mut self,
+    _gl: &mut GLContext,
+    name: &str,
+    value: &U,
+  ) -> Result<(), UniformError> {
+    let loc =
+      match self.get_uniform_location(name) {
+        None => return Err(UniformError::NotFound(String::from(name))),
+        Some(loc) => loc,
+      };
+
+    if cfg!(debug_assertions) {
+      self.check_uniform(name, value)?;
+    }
+
+    unsafe {
+      value.upload(loc);
+    }
+
+    Ok(())
+  }
+
+  /// Make the uniform block `block_name` read from the buffer attached to uniform buffer binding point `binding`.
+  pub fn bind_uniform_block(
+    &mut self,
+    _gl: &mut GLContext,
+    block_name: &str,
+    binding: GLuint,
+  ) -> Result<(), UniformError> {
+    let index =
+      match self.info.uniform_block(block_name) {
+        None => return Err(UniformError::BlockNotFound(String::from(block_name))),
+        Some(block) => block.index,
+      };
+
+    unsafe {
+      gl::UniformBlockBinding(self.handle.gl_id, index, binding);
+    }
+
+    for block in &mut self.info.uniform_blocks {
+      if block.index == index {
+        block.binding = binding;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Make the shader storage block `block_name` use the buffer attached to storage binding point `binding`.
+  pub fn bind_storage_block(
+    &mut self,
+    _gl: &mut GLContext,
+    block_name: &str,
+    binding: GLuint,
+  ) -> Result<(), UniformError> {
+    let c_name =
+      match CString::new(block_name.as_bytes()) {
+        Ok(c_name) => c_name,
+        Err(_) => return Err(UniformError::InvalidName(String::from(block_name))),
+      };
+    let index = unsafe {
+      gl::GetProgramResourceIndex(self.handle.gl_id, gl::SHADER_STORAGE_BLOCK, c_name.as_ptr() as *const GLchar)
+    };
+    if index == gl::INVALID_INDEX {
+      return Err(UniformError::BlockNotFound(String::from(block_name)));
+    }
+
+    unsafe {
+      gl::ShaderStorageBlockBinding(self.handle.gl_id, index, binding);
+    }
+
+    Ok(())
+  }
+
+  fn check_uniform<U: Uniform + ?Sized>(&self, name: &str, value: &U) -> Result<(), UniformError> {
+    // Elements of arrays (`lights[3]`) are checked against the array's declaration.
+    let (info, first_element) =
+      match self.info.uniform(name) {
+        Some(info) => (info, 0),
+        None => {
+          let element =
+            if name.ends_with(']') {
+              name.rfind('[').and_then(|i| {
+                name[i + 1 .. name.len() - 1].parse::<u32>().ok().map(|idx| (&name[..i], idx))
+              })
+            } else {
+              None
+            };
+          match element.and_then(|(base, idx)| self.info.uniform(base).map(|info| (info, idx))) {
+            Some(r) => r,
+            // Not enough reflection info to check against.
+            None => return Ok(()),
+          }
+        },
+      };
+
+    if !value.accepts(info.typ) {
+      return Err(UniformError::TypeMismatch {
+        name: String::from(name),
+        declared: info.typ,
+      });
+    }
+
+    if first_element as usize + value.count() > info.size as usize {
+      return Err(UniformError::TooManyElements {
+        name: String::from(name),
+        size: info.size - first_element.min(info.size),
+        given: value.count(),
+      });
+    }
+
+    Ok(())
+  }
+}
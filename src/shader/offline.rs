@@ -0,0 +1,223 @@
+#[cfg(feature = "naga")]
+use naga;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::error::ShaderError;
+#[cfg(feature = "naga")]
+use super::error::{Diagnostic, Severity, source_line};
+#[cfg(feature = "naga")]
+use super::include::directive;
+use super::stage::ShaderStage;
+
+#[cfg(feature = "offline_validation")]
+thread_local!(static VALIDATOR: Option<OfflineValidator> = OfflineValidator::find());
+
+/// With the `offline_validation` feature, check `source` with `OfflineValidator::find()` before it's compiled,
+/// so that every driver rejects the same shaders (some drivers accept non-conforming GLSL).
+/// Shaders that can't be checked are compiled anyway, with a warning.
+/// Without the feature, this does nothing.
+#[cfg(feature = "offline_validation")]
+pub fn check(stage: ShaderStage, source: &str) -> Result<(), ShaderError> {
+  let validation =
+    VALIDATOR.with(|validator| {
+      match *validator {
+        Some(ref validator) => validator.validate(stage, source),
+        None => Ok(Validation::Unchecked("glslangValidator isn't on PATH and the naga feature is off")),
+      }
+    })?;
+  if let Validation::Unchecked(reason) = validation {
+    warn!("{} shader not validated before compiling: {}", stage, reason);
+  }
+  Ok(())
+}
+
+#[cfg(not(feature = "offline_validation"))]
+pub fn check(_stage: ShaderStage, _source: &str) -> Result<(), ShaderError> {
+  Ok(())
+}
+
+/// Whether a shader that an `OfflineValidator` didn't reject was actually checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+  /// The shader is valid.
+  Checked,
+  /// The validator can't check this shader, so nothing is known about it. Says why.
+  Unchecked(&'static str),
+}
+
+/// Checks GLSL without an OpenGL context, e.g. on CI machines without a GPU.
+/// Errors are reported as `ShaderError`s, just like runtime compile errors.
+/// Shaders it can't check are reported as `Validation::Unchecked`, so CI can treat them as failures.
+pub enum OfflineValidator {
+  /// The `glslangValidator` reference compiler.
+  Glslang(PathBuf),
+  /// naga's GLSL frontend. It only understands Vulkan-flavoured GLSL (`#version 440` and up),
+  /// and only vertex, fragment and compute shaders; other versions and stages are `Validation::Unchecked`.
+  #[cfg(feature = "naga")]
+  Naga,
+}
+
+impl OfflineValidator {
+  /// `glslangValidator` if it's on `PATH`, otherwise naga if the `naga` feature is enabled.
+  pub fn find() -> Option<OfflineValidator> {
+    match OfflineValidator::glslang(PathBuf::from("glslangValidator")) {
+      Some(glslang) => Some(glslang),
+      None => OfflineValidator::fallback(),
+    }
+  }
+
+  #[cfg(feature = "naga")]
+  fn fallback() -> Option<OfflineValidator> {
+    Some(OfflineValidator::Naga)
+  }
+
+  #[cfg(not(feature = "naga"))]
+  fn fallback() -> Option<OfflineValidator> {
+    None
+  }
+
+  /// Use the `glslangValidator` at `path`, if it runs.
+  pub fn glslang(path: PathBuf) -> Option<OfflineValidator> {
+    let runs =
+      Command::new(&path)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if runs {
+      Some(OfflineValidator::Glslang(path))
+    } else {
+      None
+    }
+  }
+
+  pub fn validate(&self, stage: ShaderStage, source: &str) -> Result<Validation, ShaderError> {
+    match *self {
+      OfflineValidator::Glslang(ref path) => validate_glslang(path, stage, source),
+      #[cfg(feature = "naga")]
+      OfflineValidator::Naga => validate_naga(stage, source),
+    }
+  }
+
+  /// Check the components you'd pass to `Shader::new`, including whether the stages can link together.
+  /// The result is `Validation::Unchecked` if any of the components couldn't be checked.
+  pub fn validate_program<'a, T>(&self, shader_components: T) -> Result<Validation, ShaderError>
+    where T: Iterator<Item=&'a (ShaderStage, String)>,
+  {
+    let mut validation = Validation::Checked;
+    let mut stages = Vec::new();
+    for &(stage, ref source) in shader_components {
+      if let Validation::Unchecked(reason) = self.validate(stage, source)? {
+        validation = Validation::Unchecked(reason);
+      }
+      stages.push(stage);
+    }
+
+    ShaderStage::validate_combination(&stages)
+      .map_err(|message| ShaderError::message(None, message))?;
+    Ok(validation)
+  }
+}
+
+fn glslang_stage(stage: ShaderStage) -> &'static str {
+  match stage {
+    ShaderStage::Vertex         => "vert",
+    ShaderStage::Fragment       => "frag",
+    ShaderStage::Geometry       => "geom",
+    ShaderStage::TessControl    => "tesc",
+    ShaderStage::TessEvaluation => "tese",
+    ShaderStage::Compute        => "comp",
+  }
+}
+
+fn validate_glslang(path: &Path, stage: ShaderStage, source: &str) -> Result<Validation, ShaderError> {
+  let run = || -> io::Result<(bool, String)> {
+    let mut child =
+      Command::new(path)
+        .args(&["--stdin", "-S", glslang_stage(stage)])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(source.as_bytes())?;
+    let output = child.wait_with_output()?;
+    let mut log = String::from_utf8_lossy(&output.stdout).into_owned();
+    log.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.success(), log))
+  };
+
+  match run() {
+    Ok((true, _)) => Ok(Validation::Checked),
+    Ok((false, log)) => Err(ShaderError::compile(stage, log, source)),
+    Err(err) =>
+      Err(ShaderError::message(
+        Some(stage),
+        format!("couldn't run {}: {}", path.display(), err),
+      )),
+  }
+}
+
+#[cfg(feature = "naga")]
+fn validate_naga(stage: ShaderStage, source: &str) -> Result<Validation, ShaderError> {
+  let naga_stage =
+    match stage {
+      ShaderStage::Vertex   => naga::ShaderStage::Vertex,
+      ShaderStage::Fragment => naga::ShaderStage::Fragment,
+      ShaderStage::Compute  => naga::ShaderStage::Compute,
+      _ => return Ok(Validation::Unchecked("naga can't check geometry or tessellation shaders")),
+    };
+
+  let version =
+    source
+      .lines()
+      .filter_map(directive)
+      .find(|&(name, _)| name == "version")
+      .and_then(|(_, args)| args.split_whitespace().next())
+      .and_then(|version| version.parse::<u32>().ok());
+  if version.map_or(true, |version| version < 440) {
+    return Ok(Validation::Unchecked("naga can only check #version 440 and up"));
+  }
+
+  let error = |span: Option<naga::Span>, message: String| {
+    let location = span.map(|span| span.location(source));
+    let line = location.as_ref().map(|l| l.line_number);
+    Diagnostic {
+      severity: Severity::Error,
+      file: None,
+      file_name: None,
+      line: line,
+      column: location.as_ref().map(|l| l.line_position),
+      message: message,
+      excerpt: line.and_then(|line| source_line(source, line)),
+    }
+  };
+  let into_shader_error = |diagnostics: Vec<Diagnostic>| {
+    let log: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    ShaderError {
+      stage: Some(stage),
+      log: log.join("\n"),
+      diagnostics: diagnostics,
+    }
+  };
+
+  let mut frontend = naga::front::glsl::Frontend::default();
+  let module =
+    frontend.parse(&naga::front::glsl::Options::from(naga_stage), source)
+      .map_err(|errs| {
+        into_shader_error(errs.iter().map(|e| error(Some(e.meta), e.kind.to_string())).collect())
+      })?;
+
+  naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+    .validate(&module)
+    .map_err(|err| {
+      let span = err.spans().next().map(|&(span, _)| span);
+      into_shader_error(vec!(error(span, err.as_inner().to_string())))
+    })?;
+
+  Ok(Validation::Checked)
+}
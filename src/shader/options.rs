@@ -0,0 +1,87 @@
+use gl;
+use gl::types::*;
+use std::ffi::CString;
+use transform_feedback::FeedbackBufferMode;
+
+use super::error::ShaderError;
+
+fn c_name(name: &str) -> Result<CString, ShaderError> {
+  CString::new(name.as_bytes())
+    .map_err(|_| ShaderError::message(None, format!("link option name {:?} contains a NUL byte", name)))
+}
+
+/// Settings applied to a program after it's created, but before it's linked.
+#[derive(Debug, Clone)]
+pub struct LinkOptions {
+  /// `glBindAttribLocation`s: (vertex shader input, location).
+  pub attrib_locations: Vec<(String, GLuint)>,
+  /// `glBindFragDataLocation`s: (fragment shader output, color number).
+  pub frag_data_locations: Vec<(String, GLuint)>,
+  /// Outputs to capture with transform feedback, if any.
+  pub feedback_varyings: Vec<String>,
+  pub feedback_mode: FeedbackBufferMode,
+}
+
+impl LinkOptions {
+  pub fn new() -> LinkOptions {
+    LinkOptions {
+      attrib_locations: Vec::new(),
+      frag_data_locations: Vec::new(),
+      feedback_varyings: Vec::new(),
+      feedback_mode: FeedbackBufferMode::Interleaved,
+    }
+  }
+
+  /// Put the vertex shader input `name` at `location`.
+  /// Explicit `layout(location = N)` qualifiers in the shader take precedence.
+  pub fn attrib_location(mut self, name: &str, location: GLuint) -> LinkOptions {
+    self.attrib_locations.push((String::from(name), location));
+    self
+  }
+
+  /// Write the fragment shader output `name` to draw buffer `color_number`.
+  pub fn frag_data_location(mut self, name: &str, color_number: GLuint) -> LinkOptions {
+    self.frag_data_locations.push((String::from(name), color_number));
+    self
+  }
+
+  /// Capture `varyings` with transform feedback.
+  /// Varyings can be named like `particle.velocity` or `positions[2]`.
+  pub fn transform_feedback(mut self, varyings: &[&str], mode: FeedbackBufferMode) -> LinkOptions {
+    self.feedback_varyings = varyings.iter().map(|&v| String::from(v)).collect();
+    self.feedback_mode = mode;
+    self
+  }
+
+  /// Apply these options to an unlinked program. They take effect when it's linked.
+  /// Fails, without applying anything, if any of the names contain a NUL byte.
+  pub fn apply(&self, program: GLuint) -> Result<(), ShaderError> {
+    let attrib_names: Vec<CString> =
+      self.attrib_locations.iter().map(|&(ref name, _)| c_name(name)).collect::<Result<_, _>>()?;
+    let frag_data_names: Vec<CString> =
+      self.frag_data_locations.iter().map(|&(ref name, _)| c_name(name)).collect::<Result<_, _>>()?;
+    let c_varyings: Vec<CString> =
+      self.feedback_varyings.iter().map(|v| c_name(v)).collect::<Result<_, _>>()?;
+
+    for (&(_, location), c_name) in self.attrib_locations.iter().zip(attrib_names.iter()) {
+      unsafe {
+        gl::BindAttribLocation(program, location, c_name.as_ptr() as *const GLchar);
+      }
+    }
+
+    for (&(_, color_number), c_name) in self.frag_data_locations.iter().zip(frag_data_names.iter()) {
+      unsafe {
+        gl::BindFragDataLocation(program, color_number, c_name.as_ptr() as *const GLchar);
+      }
+    }
+
+    if !c_varyings.is_empty() {
+      let ptrs: Vec<*const GLchar> = c_varyings.iter().map(|v| v.as_ptr() as *const GLchar).collect();
+      unsafe {
+        gl::TransformFeedbackVaryings(program, ptrs.len() as GLsizei, ptrs.as_ptr(), self.feedback_mode.gl_enum());
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,237 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{ProgramHandle, Shader, ShaderHandle};
+use super::error::ShaderError;
+use super::reflect::{GlslType, InterfaceVariable, ProgramInfo};
+use super::stage::ShaderStage;
+
+/// A single-stage program linked with `GL_PROGRAM_SEPARABLE`, for use in a `ProgramPipeline`.
+/// Uniforms are set through `shader`, after `ProgramPipeline::make_active`.
+pub struct SeparableProgram<'a> {
+  pub shader: Shader<'a>,
+  pub stage: ShaderStage,
+  /// Active inputs and outputs, as reflected after linking. These include built-ins like `gl_Position`.
+  pub inputs: Vec<InterfaceVariable>,
+  pub outputs: Vec<InterfaceVariable>,
+}
+
+impl<'a> SeparableProgram<'a> {
+  pub fn new<'b:'a>(
+    gl: &'a GLContext,
+    stage: ShaderStage,
+    source: String,
+  ) -> Result<SeparableProgram<'b>, ShaderError> {
+    let component = ShaderHandle::compile_from(gl, source, stage)?;
+
+    let handle = ProgramHandle::new(gl);
+    let shader = Shader::link_separable(handle, vec!(component))?;
+
+    Ok(SeparableProgram {
+      inputs: ProgramInfo::query_interface(shader.handle.gl_id, gl::PROGRAM_INPUT),
+      outputs: ProgramInfo::query_interface(shader.handle.gl_id, gl::PROGRAM_OUTPUT),
+      shader: shader,
+      stage: stage,
+    })
+  }
+}
+
+/// Stages whose inputs are per-vertex arrays (e.g. `in vec3 position[]`).
+fn arrayed_inputs(stage: ShaderStage) -> bool {
+  match stage {
+    ShaderStage::TessControl | ShaderStage::TessEvaluation | ShaderStage::Geometry => true,
+    _ => false,
+  }
+}
+
+fn arrayed_outputs(stage: ShaderStage) -> bool {
+  stage == ShaderStage::TessControl
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceError {
+  /// The pipeline's stages couldn't form a program, e.g. a tessellation control stage without an evaluation stage.
+  Stages(String),
+  /// An input isn't written by the previous stage.
+  Unmatched {
+    stage: ShaderStage,
+    input: String,
+    previous: ShaderStage,
+  },
+  /// An input and the output it's matched with have different types.
+  TypeMismatch {
+    stage: ShaderStage,
+    input: String,
+    input_type: GlslType,
+    previous: ShaderStage,
+    output_type: GlslType,
+  },
+  /// An input and the output it's matched with have different array sizes.
+  SizeMismatch {
+    stage: ShaderStage,
+    input: String,
+    input_size: u32,
+    previous: ShaderStage,
+    output_size: u32,
+  },
+}
+
+impl fmt::Display for InterfaceError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      InterfaceError::Stages(ref message) =>
+        write!(f, "incomplete program pipeline: {}", message),
+      InterfaceError::Unmatched { stage, ref input, previous } =>
+        write!(f, "{} input {} isn't written by the {} stage", stage, input, previous),
+      InterfaceError::TypeMismatch { stage, ref input, input_type, previous, output_type } =>
+        write!(
+          f,
+          "{} input {} is a {}, but the {} stage writes a {}",
+          stage, input, input_type, previous, output_type,
+        ),
+      InterfaceError::SizeMismatch { stage, ref input, input_size, previous, output_size } =>
+        write!(
+          f,
+          "{} input {} has {} elements, but the {} stage writes {}",
+          stage, input, input_size, previous, output_size,
+        ),
+    }
+  }
+}
+
+impl std::error::Error for InterfaceError {}
+
+/// Mixes and matches the stages of `SeparableProgram`s.
+/// The pipeline borrows the programs it uses, so they can't be deleted out from under it.
+pub struct ProgramPipeline<'a> {
+  pub gl_id: GLuint,
+  stages: HashMap<ShaderStage, &'a SeparableProgram<'a>>,
+  phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> ProgramPipeline<'a> {
+  pub fn new<'b:'a>(_gl: &'a GLContext) -> ProgramPipeline<'b> {
+    let mut gl_id = 0;
+    unsafe {
+      gl::GenProgramPipelines(1, &mut gl_id);
+    }
+
+    assert!(gl_id != 0);
+
+    ProgramPipeline {
+      gl_id: gl_id,
+      stages: HashMap::new(),
+      phantom: PhantomData,
+    }
+  }
+
+  /// Use `program` for its stage, replacing whatever was there.
+  /// Call `validate` once all the stages are in place.
+  pub fn use_stage(&mut self, _gl: &mut GLContext, program: &'a SeparableProgram<'a>) {
+    unsafe {
+      gl::UseProgramStages(self.gl_id, program.stage.gl_bit(), program.shader.handle.gl_id);
+    }
+    self.stages.insert(program.stage, program);
+  }
+
+  pub fn clear_stage(&mut self, _gl: &mut GLContext, stage: ShaderStage) {
+    unsafe {
+      gl::UseProgramStages(self.gl_id, stage.gl_bit(), 0);
+    }
+    self.stages.remove(&stage);
+  }
+
+  /// Direct `glUniform*` calls (e.g. `Shader::set_uniform`) at `program`.
+  /// N.B. For performance reasons, this does NOT bind the pipeline.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn make_active(&self, _gl: &mut GLContext, program: &SeparableProgram) {
+    unsafe {
+      gl::ActiveShaderProgram(self.gl_id, program.shader.handle.gl_id);
+    }
+  }
+
+  /// Unbinds any program bound with `glUseProgram`, which would take precedence over the pipeline.
+  pub fn bind(&self, _gl: &mut GLContext) {
+    unsafe {
+      gl::UseProgram(0);
+      gl::BindProgramPipeline(self.gl_id);
+    }
+  }
+
+  pub fn stages(&self) -> Vec<ShaderStage> {
+    let mut stages: Vec<ShaderStage> = self.stages.keys().cloned().collect();
+    stages.sort_by_key(|s| ShaderStage::pipeline_order().iter().position(|o| o == s));
+    stages
+  }
+
+  /// Check that the stages form a complete program, and that every input of each stage
+  /// is written, with the same type, by the stage before it.
+  /// Inputs are matched to outputs by location when both have one, and by name otherwise.
+  pub fn validate(&self) -> Result<(), InterfaceError> {
+    let stages = self.stages();
+    ShaderStage::validate_combination(&stages).map_err(InterfaceError::Stages)?;
+
+    for pair in stages.windows(2) {
+      let (previous, stage) = (pair[0], pair[1]);
+      let outputs = &self.stages[&previous].outputs;
+      let check_sizes = !arrayed_outputs(previous) && !arrayed_inputs(stage);
+
+      for input in self.stages[&stage].inputs.iter().filter(|v| !v.is_builtin()) {
+        let output =
+          outputs
+            .iter()
+            .find(|o| input.location.is_some() && o.location == input.location)
+            .or_else(|| outputs.iter().find(|o| o.name == input.name));
+        let output =
+          match output {
+            None =>
+              return Err(InterfaceError::Unmatched {
+                stage: stage,
+                input: input.name.clone(),
+                previous: previous,
+              }),
+            Some(output) => output,
+          };
+
+        if output.typ != input.typ {
+          return Err(InterfaceError::TypeMismatch {
+            stage: stage,
+            input: input.name.clone(),
+            input_type: input.typ,
+            previous: previous,
+            output_type: output.typ,
+          });
+        }
+        if check_sizes && output.size != input.size {
+          return Err(InterfaceError::SizeMismatch {
+            stage: stage,
+            input: input.name.clone(),
+            input_size: input.size,
+            previous: previous,
+            output_size: output.size,
+          });
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// The program currently used for `stage`, if any.
+  pub fn program_for(&self, stage: ShaderStage) -> Option<&'a SeparableProgram<'a>> {
+    self.stages.get(&stage).cloned()
+  }
+}
+
+impl<'a> Drop for ProgramPipeline<'a> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteProgramPipelines(1, &self.gl_id);
+    }
+  }
+}
@@ -0,0 +1,482 @@
+use gl;
+use gl::types::*;
+use std::ffi::CString;
+use std::fmt;
+use std::iter::repeat;
+use std::ptr;
+
+/// The type of an active uniform or attribute, as reported by OpenGL.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GlslType {
+  Float, Vec2, Vec3, Vec4,
+  Double, DVec2, DVec3, DVec4,
+  Int, IVec2, IVec3, IVec4,
+  UInt, UVec2, UVec3, UVec4,
+  Bool, BVec2, BVec3, BVec4,
+  Mat2, Mat3, Mat4,
+  Mat2x3, Mat2x4, Mat3x2, Mat3x4, Mat4x2, Mat4x3,
+  Sampler1D, Sampler2D, Sampler3D, SamplerCube,
+  Sampler1DShadow, Sampler2DShadow, SamplerCubeShadow,
+  Sampler1DArray, Sampler2DArray, Sampler2DArrayShadow,
+  Sampler2DRect, Sampler2DMultisample, SamplerBuffer,
+  ISampler2D, ISampler3D, ISamplerCube, ISampler2DArray, ISamplerBuffer,
+  USampler2D, USampler3D, USamplerCube, USampler2DArray, USamplerBuffer,
+  /// A type this enum doesn't know about.
+  Other(GLenum),
+}
+
+impl GlslType {
+  pub fn from_gl(typ: GLenum) -> GlslType {
+    match typ {
+      gl::FLOAT                         => GlslType::Float,
+      gl::FLOAT_VEC2                    => GlslType::Vec2,
+      gl::FLOAT_VEC3                    => GlslType::Vec3,
+      gl::FLOAT_VEC4                    => GlslType::Vec4,
+      gl::DOUBLE                        => GlslType::Double,
+      gl::DOUBLE_VEC2                   => GlslType::DVec2,
+      gl::DOUBLE_VEC3                   => GlslType::DVec3,
+      gl::DOUBLE_VEC4                   => GlslType::DVec4,
+      gl::INT                           => GlslType::Int,
+      gl::INT_VEC2                      => GlslType::IVec2,
+      gl::INT_VEC3                      => GlslType::IVec3,
+      gl::INT_VEC4                      => GlslType::IVec4,
+      gl::UNSIGNED_INT                  => GlslType::UInt,
+      gl::UNSIGNED_INT_VEC2             => GlslType::UVec2,
+      gl::UNSIGNED_INT_VEC3             => GlslType::UVec3,
+      gl::UNSIGNED_INT_VEC4             => GlslType::UVec4,
+      gl::BOOL                          => GlslType::Bool,
+      gl::BOOL_VEC2                     => GlslType::BVec2,
+      gl::BOOL_VEC3                     => GlslType::BVec3,
+      gl::BOOL_VEC4                     => GlslType::BVec4,
+      gl::FLOAT_MAT2                    => GlslType::Mat2,
+      gl::FLOAT_MAT3                    => GlslType::Mat3,
+      gl::FLOAT_MAT4                    => GlslType::Mat4,
+      gl::FLOAT_MAT2x3                  => GlslType::Mat2x3,
+      gl::FLOAT_MAT2x4                  => GlslType::Mat2x4,
+      gl::FLOAT_MAT3x2                  => GlslType::Mat3x2,
+      gl::FLOAT_MAT3x4                  => GlslType::Mat3x4,
+      gl::FLOAT_MAT4x2                  => GlslType::Mat4x2,
+      gl::FLOAT_MAT4x3                  => GlslType::Mat4x3,
+      gl::SAMPLER_1D                    => GlslType::Sampler1D,
+      gl::SAMPLER_2D                    => GlslType::Sampler2D,
+      gl::SAMPLER_3D                    => GlslType::Sampler3D,
+      gl::SAMPLER_CUBE                  => GlslType::SamplerCube,
+      gl::SAMPLER_1D_SHADOW             => GlslType::Sampler1DShadow,
+      gl::SAMPLER_2D_SHADOW             => GlslType::Sampler2DShadow,
+      gl::SAMPLER_CUBE_SHADOW           => GlslType::SamplerCubeShadow,
+      gl::SAMPLER_1D_ARRAY              => GlslType::Sampler1DArray,
+      gl::SAMPLER_2D_ARRAY              => GlslType::Sampler2DArray,
+      gl::SAMPLER_2D_ARRAY_SHADOW       => GlslType::Sampler2DArrayShadow,
+      gl::SAMPLER_2D_RECT               => GlslType::Sampler2DRect,
+      gl::SAMPLER_2D_MULTISAMPLE        => GlslType::Sampler2DMultisample,
+      gl::SAMPLER_BUFFER                => GlslType::SamplerBuffer,
+      gl::INT_SAMPLER_2D                => GlslType::ISampler2D,
+      gl::INT_SAMPLER_3D                => GlslType::ISampler3D,
+      gl::INT_SAMPLER_CUBE              => GlslType::ISamplerCube,
+      gl::INT_SAMPLER_2D_ARRAY          => GlslType::ISampler2DArray,
+      gl::INT_SAMPLER_BUFFER            => GlslType::ISamplerBuffer,
+      gl::UNSIGNED_INT_SAMPLER_2D       => GlslType::USampler2D,
+      gl::UNSIGNED_INT_SAMPLER_3D       => GlslType::USampler3D,
+      gl::UNSIGNED_INT_SAMPLER_CUBE     => GlslType::USamplerCube,
+      gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => GlslType::USampler2DArray,
+      gl::UNSIGNED_INT_SAMPLER_BUFFER   => GlslType::USamplerBuffer,
+      typ                               => GlslType::Other(typ),
+    }
+  }
+
+  /// The GLSL spelling of this type.
+  pub fn name(&self) -> String {
+    let name =
+      match *self {
+        GlslType::Float                => "float",
+        GlslType::Vec2                 => "vec2",
+        GlslType::Vec3                 => "vec3",
+        GlslType::Vec4                 => "vec4",
+        GlslType::Double               => "double",
+        GlslType::DVec2                => "dvec2",
+        GlslType::DVec3                => "dvec3",
+        GlslType::DVec4                => "dvec4",
+        GlslType::Int                  => "int",
+        GlslType::IVec2                => "ivec2",
+        GlslType::IVec3                => "ivec3",
+        GlslType::IVec4                => "ivec4",
+        GlslType::UInt                 => "uint",
+        GlslType::UVec2                => "uvec2",
+        GlslType::UVec3                => "uvec3",
+        GlslType::UVec4                => "uvec4",
+        GlslType::Bool                 => "bool",
+        GlslType::BVec2                => "bvec2",
+        GlslType::BVec3                => "bvec3",
+        GlslType::BVec4                => "bvec4",
+        GlslType::Mat2                 => "mat2",
+        GlslType::Mat3                 => "mat3",
+        GlslType::Mat4                 => "mat4",
+        GlslType::Mat2x3               => "mat2x3",
+        GlslType::Mat2x4               => "mat2x4",
+        GlslType::Mat3x2               => "mat3x2",
+        GlslType::Mat3x4               => "mat3x4",
+        GlslType::Mat4x2               => "mat4x2",
+        GlslType::Mat4x3               => "mat4x3",
+        GlslType::Sampler1D            => "sampler1D",
+        GlslType::Sampler2D            => "sampler2D",
+        GlslType::Sampler3D            => "sampler3D",
+        GlslType::SamplerCube          => "samplerCube",
+        GlslType::Sampler1DShadow      => "sampler1DShadow",
+        GlslType::Sampler2DShadow      => "sampler2DShadow",
+        GlslType::SamplerCubeShadow    => "samplerCubeShadow",
+        GlslType::Sampler1DArray       => "sampler1DArray",
+        GlslType::Sampler2DArray       => "sampler2DArray",
+        GlslType::Sampler2DArrayShadow => "sampler2DArrayShadow",
+        GlslType::Sampler2DRect        => "sampler2DRect",
+        GlslType::Sampler2DMultisample => "sampler2DMS",
+        GlslType::SamplerBuffer        => "samplerBuffer",
+        GlslType::ISampler2D           => "isampler2D",
+        GlslType::ISampler3D           => "isampler3D",
+        GlslType::ISamplerCube         => "isamplerCube",
+        GlslType::ISampler2DArray      => "isampler2DArray",
+        GlslType::ISamplerBuffer       => "isamplerBuffer",
+        GlslType::USampler2D           => "usampler2D",
+        GlslType::USampler3D           => "usampler3D",
+        GlslType::USamplerCube         => "usamplerCube",
+        GlslType::USampler2DArray      => "usampler2DArray",
+        GlslType::USamplerBuffer       => "usamplerBuffer",
+        GlslType::Other(typ)           => return format!("<type 0x{:x}>", typ),
+      };
+    String::from(name)
+  }
+
+  pub fn is_sampler(&self) -> bool {
+    match *self {
+      GlslType::Sampler1D | GlslType::Sampler2D | GlslType::Sampler3D | GlslType::SamplerCube |
+      GlslType::Sampler1DShadow | GlslType::Sampler2DShadow | GlslType::SamplerCubeShadow |
+      GlslType::Sampler1DArray | GlslType::Sampler2DArray | GlslType::Sampler2DArrayShadow |
+      GlslType::Sampler2DRect | GlslType::Sampler2DMultisample | GlslType::SamplerBuffer |
+      GlslType::ISampler2D | GlslType::ISampler3D | GlslType::ISamplerCube |
+      GlslType::ISampler2DArray | GlslType::ISamplerBuffer |
+      GlslType::USampler2D | GlslType::USampler3D | GlslType::USamplerCube |
+      GlslType::USampler2DArray | GlslType::USamplerBuffer => true,
+      _ => false,
+    }
+  }
+}
+
+impl fmt::Display for GlslType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct UniformInfo {
+  /// As reported by OpenGL, e.g. `lights[0].color`. Arrays of basic types end in `[0]`.
+  pub name: String,
+  pub typ: GlslType,
+  /// Number of array elements; 1 for non-arrays.
+  pub size: u32,
+  /// `None` for uniforms inside a uniform block.
+  pub location: Option<GLint>,
+  /// The uniform block this is a member of, if any.
+  pub block_index: Option<u32>,
+  /// Byte offset into the uniform block.
+  pub offset: Option<u32>,
+  /// Byte stride between array elements in a uniform block.
+  pub array_stride: Option<u32>,
+  /// Byte stride between matrix columns (or rows) in a uniform block.
+  pub matrix_stride: Option<u32>,
+  pub row_major: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttributeInfo {
+  pub name: String,
+  pub typ: GlslType,
+  /// Number of array elements; 1 for non-arrays.
+  pub size: u32,
+  /// `None` for built-ins like `gl_VertexID`.
+  pub location: Option<GLint>,
+}
+
+/// A stage input or output, as seen through the program interface query API.
+#[derive(Debug, Clone)]
+pub struct InterfaceVariable {
+  pub name: String,
+  pub typ: GlslType,
+  /// Number of array elements; 1 for non-arrays.
+  pub size: u32,
+  /// `None` for built-ins, and for variables without an assigned location.
+  pub location: Option<GLint>,
+}
+
+impl InterfaceVariable {
+  pub fn is_builtin(&self) -> bool {
+    self.name.starts_with("gl_")
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct UniformBlockInfo {
+  pub name: String,
+  pub index: u32,
+  pub binding: u32,
+  /// Minimum buffer size, in bytes, needed to back this block.
+  pub data_size: u32,
+  /// Indices into `ProgramInfo::uniforms`.
+  pub members: Vec<usize>,
+}
+
+/// Everything OpenGL reports about a linked program's active resources.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramInfo {
+  pub uniforms: Vec<UniformInfo>,
+  pub attributes: Vec<AttributeInfo>,
+  pub uniform_blocks: Vec<UniformBlockInfo>,
+}
+
+fn program_iv(program: GLuint, pname: GLenum) -> GLint {
+  let mut r = 0;
+  unsafe {
+    gl::GetProgramiv(program, pname, &mut r);
+  }
+  r
+}
+
+fn name_from_buf(mut buf: Vec<u8>, len: GLsizei) -> String {
+  buf.truncate(len as usize);
+  String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn non_negative(x: GLint) -> Option<u32> {
+  if x < 0 {
+    None
+  } else {
+    Some(x as u32)
+  }
+}
+
+impl ProgramInfo {
+  /// Query the active resources of a successfully linked program.
+  pub fn query(program: GLuint) -> ProgramInfo {
+    let uniforms = ProgramInfo::query_uniforms(program);
+    let uniform_blocks = ProgramInfo::query_uniform_blocks(program, &uniforms);
+    ProgramInfo {
+      uniforms: uniforms,
+      attributes: ProgramInfo::query_attributes(program),
+      uniform_blocks: uniform_blocks,
+    }
+  }
+
+  fn query_uniforms(program: GLuint) -> Vec<UniformInfo> {
+    let count = program_iv(program, gl::ACTIVE_UNIFORMS);
+    let max_len = program_iv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH);
+
+    let mut uniforms = Vec::new();
+    for i in 0 .. count as GLuint {
+      let mut buf: Vec<u8> = repeat(0).take(max_len as usize + 1).collect();
+      let mut len = 0;
+      let mut size = 0;
+      let mut typ = 0;
+      unsafe {
+        gl::GetActiveUniform(
+          program,
+          i,
+          buf.len() as GLsizei,
+          &mut len,
+          &mut size,
+          &mut typ,
+          buf.as_mut_ptr() as *mut GLchar,
+        );
+      }
+      let name = name_from_buf(buf, len);
+
+      let uniform_iv = |pname| {
+        let mut r = 0;
+        unsafe {
+          gl::GetActiveUniformsiv(program, 1, &i, pname, &mut r);
+        }
+        r
+      };
+
+      let block_index = non_negative(uniform_iv(gl::UNIFORM_BLOCK_INDEX));
+      let location =
+        match block_index {
+          Some(_) => None,
+          None => {
+            let c_name = CString::new(name.as_bytes()).unwrap();
+            let loc = unsafe {
+              gl::GetUniformLocation(program, c_name.as_ptr() as *const GLchar)
+            };
+            if loc == -1 { None } else { Some(loc) }
+          },
+        };
+
+      uniforms.push(UniformInfo {
+        typ: GlslType::from_gl(typ),
+        size: size as u32,
+        location: location,
+        block_index: block_index,
+        offset: non_negative(uniform_iv(gl::UNIFORM_OFFSET)),
+        array_stride: block_index.and(non_negative(uniform_iv(gl::UNIFORM_ARRAY_STRIDE))),
+        matrix_stride: block_index.and(non_negative(uniform_iv(gl::UNIFORM_MATRIX_STRIDE))),
+        row_major: uniform_iv(gl::UNIFORM_IS_ROW_MAJOR) != 0,
+        name: name,
+      });
+    }
+
+    uniforms
+  }
+
+  fn query_attributes(program: GLuint) -> Vec<AttributeInfo> {
+    let count = program_iv(program, gl::ACTIVE_ATTRIBUTES);
+    let max_len = program_iv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH);
+
+    let mut attributes = Vec::new();
+    for i in 0 .. count as GLuint {
+      let mut buf: Vec<u8> = repeat(0).take(max_len as usize + 1).collect();
+      let mut len = 0;
+      let mut size = 0;
+      let mut typ = 0;
+      unsafe {
+        gl::GetActiveAttrib(
+          program,
+          i,
+          buf.len() as GLsizei,
+          &mut len,
+          &mut size,
+          &mut typ,
+          buf.as_mut_ptr() as *mut GLchar,
+        );
+      }
+      let name = name_from_buf(buf, len);
+
+      let c_name = CString::new(name.as_bytes()).unwrap();
+      let location = unsafe {
+        gl::GetAttribLocation(program, c_name.as_ptr() as *const GLchar)
+      };
+
+      attributes.push(AttributeInfo {
+        name: name,
+        typ: GlslType::from_gl(typ),
+        size: size as u32,
+        location: if location == -1 { None } else { Some(location) },
+      });
+    }
+
+    attributes
+  }
+
+  fn query_uniform_blocks(program: GLuint, uniforms: &[UniformInfo]) -> Vec<UniformBlockInfo> {
+    let count = program_iv(program, gl::ACTIVE_UNIFORM_BLOCKS);
+    let max_len = program_iv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH);
+
+    let mut blocks = Vec::new();
+    for i in 0 .. count as GLuint {
+      let mut buf: Vec<u8> = repeat(0).take(max_len as usize + 1).collect();
+      let mut len = 0;
+      unsafe {
+        gl::GetActiveUniformBlockName(
+          program,
+          i,
+          buf.len() as GLsizei,
+          &mut len,
+          buf.as_mut_ptr() as *mut GLchar,
+        );
+      }
+
+      let block_iv = |pname| {
+        let mut r = 0;
+        unsafe {
+          gl::GetActiveUniformBlockiv(program, i, pname, &mut r);
+        }
+        r
+      };
+
+      blocks.push(UniformBlockInfo {
+        name: name_from_buf(buf, len),
+        index: i,
+        binding: block_iv(gl::UNIFORM_BLOCK_BINDING) as u32,
+        data_size: block_iv(gl::UNIFORM_BLOCK_DATA_SIZE) as u32,
+        members:
+          uniforms
+            .iter()
+            .enumerate()
+            .filter(|&(_, u)| u.block_index == Some(i))
+            .map(|(j, _)| j)
+            .collect(),
+      });
+    }
+
+    blocks
+  }
+
+  /// Query the active inputs (`gl::PROGRAM_INPUT`) or outputs (`gl::PROGRAM_OUTPUT`) of a linked program.
+  /// Needs OpenGL 4.3 or ARB_program_interface_query.
+  pub fn query_interface(program: GLuint, interface: GLenum) -> Vec<InterfaceVariable> {
+    let mut count = 0;
+    let mut max_len = 0;
+    unsafe {
+      gl::GetProgramInterfaceiv(program, interface, gl::ACTIVE_RESOURCES, &mut count);
+      gl::GetProgramInterfaceiv(program, interface, gl::MAX_NAME_LENGTH, &mut max_len);
+    }
+
+    let props = [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION];
+    let mut variables = Vec::new();
+    for i in 0 .. count as GLuint {
+      let mut buf: Vec<u8> = repeat(0).take(max_len as usize + 1).collect();
+      let mut len = 0;
+      let mut values = [0; 3];
+      unsafe {
+        gl::GetProgramResourceName(
+          program,
+          interface,
+          i,
+          buf.len() as GLsizei,
+          &mut len,
+          buf.as_mut_ptr() as *mut GLchar,
+        );
+        gl::GetProgramResourceiv(
+          program,
+          interface,
+          i,
+          props.len() as GLsizei,
+          props.as_ptr(),
+          values.len() as GLsizei,
+          ptr::null_mut(),
+          values.as_mut_ptr(),
+        );
+      }
+
+      variables.push(InterfaceVariable {
+        name: name_from_buf(buf, len),
+        typ: GlslType::from_gl(values[0] as GLenum),
+        size: values[1].max(1) as u32,
+        location: if values[2] == -1 { None } else { Some(values[2]) },
+      });
+    }
+
+    variables
+  }
+
+  /// Find a uniform by name. Arrays can be found with or without their trailing `[0]`.
+  pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+    self.uniforms.iter().find(|u| array_name_matches(&u.name, name))
+  }
+
+  /// Find an attribute by name. Arrays can be found with or without their trailing `[0]`.
+  pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+    self.attributes.iter().find(|a| array_name_matches(&a.name, name))
+  }
+
+  pub fn uniform_block(&self, name: &str) -> Option<&UniformBlockInfo> {
+    self.uniform_blocks.iter().find(|b| b.name == name)
+  }
+
+  /// The uniforms that make up a block, in the order OpenGL reported them.
+  pub fn block_members<'a>(&'a self, block: &'a UniformBlockInfo) -> Vec<&'a UniformInfo> {
+    block.members.iter().map(|&i| &self.uniforms[i]).collect()
+  }
+}
+
+fn array_name_matches(reported: &str, name: &str) -> bool {
+  reported == name ||
+    (reported.ends_with("[0]") && &reported[.. reported.len() - 3] == name)
+}
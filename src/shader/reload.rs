@@ -0,0 +1,258 @@
+use gl_context::GLContext;
+use std::collections::HashMap;
+#[cfg(feature = "notify")]
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "notify")]
+use notify;
+#[cfg(feature = "notify")]
+use notify::Watcher;
+#[cfg(feature = "notify")]
+use std::sync::mpsc;
+#[cfg(feature = "notify")]
+use std::time::Duration;
+
+use super::{Shader, ShaderHandle};
+use super::error::ShaderError;
+use super::include::{FileResolver, IncludeError, preprocess_file};
+use super::stage::ShaderStage;
+
+fn modified(path: &Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Files that don't exist yet can't be watched, so watch the directory they'd be created in.
+#[cfg(feature = "notify")]
+fn watch_path(path: &Path) -> &Path {
+  match path.parent() {
+    Some(dir) if !path.exists() && !dir.as_os_str().is_empty() => dir,
+    _ => path,
+  }
+}
+
+/// The files an `IncludeError` is about. Creating or fixing any of them might fix the error.
+fn error_files(resolver: &FileResolver, err: &IncludeError) -> Vec<PathBuf> {
+  match *err {
+    IncludeError::Resolve { ref path, ref includer, .. } => {
+      let mut files = resolver.candidates(path, includer);
+      if !includer.is_empty() {
+        files.push(PathBuf::from(includer));
+      }
+      files
+    },
+    IncludeError::Cycle(ref chain) => chain.iter().map(PathBuf::from).collect(),
+    IncludeError::Malformed { ref file, .. } => vec!(PathBuf::from(file)),
+  }
+}
+
+/// A `Shader` built from files on disk, which can be rebuilt when those files change.
+/// `#include`s are resolved through `resolver`, and included files are watched too.
+pub struct ReloadableShader<'a> {
+  pub stages: Vec<(ShaderStage, PathBuf)>,
+  pub resolver: FileResolver,
+  shader: Shader<'a>,
+  /// Incremented every time `shader` is replaced.
+  /// Anything derived from the old program (e.g. uniform locations) is stale once this changes.
+  generation: u32,
+  last_error: Option<ShaderError>,
+  /// Every file that went into `shader`, and when it was last modified.
+  files: HashMap<PathBuf, Option<SystemTime>>,
+  /// If the last reload failed, every file it read or tried to read
+  /// (e.g. a newly `#include`d file that doesn't exist yet).
+  failed_files: HashMap<PathBuf, Option<SystemTime>>,
+  #[cfg(feature = "notify")]
+  watcher: Option<(notify::RecommendedWatcher, mpsc::Receiver<notify::DebouncedEvent>)>,
+  /// The paths `watcher` is watching.
+  #[cfg(feature = "notify")]
+  watched: HashSet<PathBuf>,
+}
+
+impl<'a> ReloadableShader<'a> {
+  pub fn new(
+    gl: &GLContext,
+    stages: Vec<(ShaderStage, PathBuf)>,
+    resolver: FileResolver,
+  ) -> Result<ReloadableShader<'a>, ShaderError> {
+    let mut files = HashMap::new();
+    let shader = ReloadableShader::build(gl, &stages, &resolver, &mut files)?;
+    Ok(ReloadableShader {
+      stages: stages,
+      resolver: resolver,
+      shader: shader,
+      generation: 0,
+      last_error: None,
+      files: files,
+      failed_files: HashMap::new(),
+      #[cfg(feature = "notify")]
+      watcher: None,
+      #[cfg(feature = "notify")]
+      watched: HashSet::new(),
+    })
+  }
+
+  /// Adds every file the build read, or tried to read, to `files`, even if it fails,
+  /// so that creating or fixing any of them triggers another try.
+  fn build(
+    gl: &GLContext,
+    stages: &[(ShaderStage, PathBuf)],
+    resolver: &FileResolver,
+    files: &mut HashMap<PathBuf, Option<SystemTime>>,
+  ) -> Result<Shader<'a>, ShaderError> {
+    let mut components = Vec::new();
+    for &(stage, ref path) in stages {
+      let preprocessed =
+        match preprocess_file(resolver, &path.to_string_lossy()) {
+          Ok(preprocessed) => preprocessed,
+          Err(err) => {
+            for path in error_files(resolver, &err) {
+              let mtime = modified(&path);
+              files.insert(path, mtime);
+            }
+            return Err(err.into_shader_error(stage));
+          },
+        };
+      for file in &preprocessed.files {
+        let path = PathBuf::from(&file.name);
+        let mtime = modified(&path);
+        files.insert(path, mtime);
+      }
+      components.push(ShaderHandle::compile_preprocessed(gl, &preprocessed, stage)?);
+    }
+
+    Shader::link(gl, components)
+  }
+
+  /// The most recent program that built successfully.
+  pub fn shader(&self) -> &Shader<'a> {
+    &self.shader
+  }
+
+  pub fn shader_mut(&mut self) -> &mut Shader<'a> {
+    &mut self.shader
+  }
+
+  pub fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// The error from the last reload, if it failed.
+  /// Cleared by the next successful reload.
+  pub fn error(&self) -> Option<&ShaderError> {
+    self.last_error.as_ref()
+  }
+
+  /// Rebuild from disk, regardless of whether anything changed.
+  /// On success, the program is replaced (which drops its cached uniform locations) and this returns true.
+  /// On failure, the previous program is kept, and the error is available from `error`.
+  pub fn reload(&mut self, gl: &GLContext) -> bool {
+    let mut files = HashMap::new();
+    let reloaded =
+      match ReloadableShader::build(gl, &self.stages, &self.resolver, &mut files) {
+        Ok(shader) => {
+          self.shader = shader;
+          self.generation += 1;
+          self.last_error = None;
+          self.files = files;
+          self.failed_files.clear();
+          true
+        },
+        Err(err) => {
+          warn!("Keeping previous shader program: {}", err);
+          // Keep watching the files the last good build used, as well as the ones this build tried to use.
+          // Remember the new modification times anyway, so we don't retry until the files change again.
+          for (path, mtime) in &mut self.files {
+            *mtime = modified(path);
+          }
+          self.failed_files = files;
+          self.last_error = Some(err);
+          false
+        },
+      };
+    #[cfg(feature = "notify")]
+    self.rewatch();
+    reloaded
+  }
+
+  /// Reload if any of the source files have changed since the last build.
+  /// Returns true if the program was replaced.
+  pub fn poll(&mut self, gl: &GLContext) -> bool {
+    if self.changed() {
+      self.reload(gl)
+    } else {
+      false
+    }
+  }
+
+  fn modified_since_build(&self) -> bool {
+    self.files.iter().chain(self.failed_files.iter()).any(|(path, &mtime)| modified(path) != mtime)
+  }
+
+  #[cfg(not(feature = "notify"))]
+  fn changed(&mut self) -> bool {
+    self.modified_since_build()
+  }
+
+  #[cfg(feature = "notify")]
+  fn changed(&mut self) -> bool {
+    match self.watcher {
+      None => self.modified_since_build(),
+      Some((_, ref events)) => {
+        let mut changed = false;
+        while let Ok(event) = events.try_recv() {
+          match event {
+            notify::DebouncedEvent::NoticeWrite(_) |
+            notify::DebouncedEvent::NoticeRemove(_) |
+            notify::DebouncedEvent::Rescan => {},
+            _ => changed = true,
+          }
+        }
+        changed
+      },
+    }
+  }
+
+  /// Use filesystem notifications instead of polling modification times.
+  #[cfg(feature = "notify")]
+  pub fn watch(&mut self, delay: Duration) -> notify::Result<()> {
+    let (send, recv) = mpsc::channel();
+    let mut watcher = notify::watcher(send, delay)?;
+    let paths = self.watch_paths();
+    for path in &paths {
+      watcher.watch(path, notify::RecursiveMode::NonRecursive)?;
+    }
+    self.watcher = Some((watcher, recv));
+    self.watched = paths;
+    Ok(())
+  }
+
+  /// What to watch for the current set of files.
+  #[cfg(feature = "notify")]
+  fn watch_paths(&self) -> HashSet<PathBuf> {
+    self.files.keys()
+      .chain(self.failed_files.keys())
+      .map(|path| watch_path(path).to_path_buf())
+      .collect()
+  }
+
+  /// The set of files may have changed: watch the new ones, and stop watching the ones that are no longer used.
+  #[cfg(feature = "notify")]
+  fn rewatch(&mut self) {
+    let paths = self.watch_paths();
+    if let Some((ref mut watcher, _)) = self.watcher {
+      for path in self.watched.difference(&paths) {
+        if let Err(err) = watcher.unwatch(path) {
+          warn!("Couldn't stop watching {}: {:?}", path.display(), err);
+        }
+      }
+      for path in paths.difference(&self.watched) {
+        if let Err(err) = watcher.watch(path, notify::RecursiveMode::NonRecursive) {
+          warn!("Couldn't watch {}: {:?}", path.display(), err);
+        }
+      }
+      self.watched = paths;
+    }
+  }
+}
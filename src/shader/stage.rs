@@ -0,0 +1,121 @@
+use gl;
+use gl::types::*;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+  Vertex,
+  Fragment,
+  Geometry,
+  TessControl,
+  TessEvaluation,
+  Compute,
+}
+
+impl ShaderStage {
+  pub fn gl_enum(&self) -> GLenum {
+    match *self {
+      ShaderStage::Vertex         => gl::VERTEX_SHADER,
+      ShaderStage::Fragment       => gl::FRAGMENT_SHADER,
+      ShaderStage::Geometry       => gl::GEOMETRY_SHADER,
+      ShaderStage::TessControl    => gl::TESS_CONTROL_SHADER,
+      ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+      ShaderStage::Compute        => gl::COMPUTE_SHADER,
+    }
+  }
+
+  pub fn from_gl(typ: GLenum) -> Option<ShaderStage> {
+    match typ {
+      gl::VERTEX_SHADER          => Some(ShaderStage::Vertex),
+      gl::FRAGMENT_SHADER        => Some(ShaderStage::Fragment),
+      gl::GEOMETRY_SHADER        => Some(ShaderStage::Geometry),
+      gl::TESS_CONTROL_SHADER    => Some(ShaderStage::TessControl),
+      gl::TESS_EVALUATION_SHADER => Some(ShaderStage::TessEvaluation),
+      gl::COMPUTE_SHADER         => Some(ShaderStage::Compute),
+      _                          => None,
+    }
+  }
+
+  /// The bit for `glUseProgramStages`.
+  pub fn gl_bit(&self) -> GLbitfield {
+    match *self {
+      ShaderStage::Vertex         => gl::VERTEX_SHADER_BIT,
+      ShaderStage::Fragment       => gl::FRAGMENT_SHADER_BIT,
+      ShaderStage::Geometry       => gl::GEOMETRY_SHADER_BIT,
+      ShaderStage::TessControl    => gl::TESS_CONTROL_SHADER_BIT,
+      ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER_BIT,
+      ShaderStage::Compute        => gl::COMPUTE_SHADER_BIT,
+    }
+  }
+
+  /// Infer a stage from a conventional file extension, e.g. `vert` or `frag`.
+  pub fn from_extension(extension: &str) -> Option<ShaderStage> {
+    match extension {
+      "vert" => Some(ShaderStage::Vertex),
+      "frag" => Some(ShaderStage::Fragment),
+      "geom" => Some(ShaderStage::Geometry),
+      "tesc" => Some(ShaderStage::TessControl),
+      "tese" => Some(ShaderStage::TessEvaluation),
+      "comp" => Some(ShaderStage::Compute),
+      _      => None,
+    }
+  }
+
+  pub fn from_path(path: &Path) -> Option<ShaderStage> {
+    path.extension()
+      .and_then(|e| e.to_str())
+      .and_then(ShaderStage::from_extension)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match *self {
+      ShaderStage::Vertex         => "vertex",
+      ShaderStage::Fragment       => "fragment",
+      ShaderStage::Geometry       => "geometry",
+      ShaderStage::TessControl    => "tessellation control",
+      ShaderStage::TessEvaluation => "tessellation evaluation",
+      ShaderStage::Compute        => "compute",
+    }
+  }
+
+  /// The order data flows through the graphics stages.
+  pub fn pipeline_order() -> [ShaderStage; 5] {
+    [
+      ShaderStage::Vertex,
+      ShaderStage::TessControl,
+      ShaderStage::TessEvaluation,
+      ShaderStage::Geometry,
+      ShaderStage::Fragment,
+    ]
+  }
+
+  /// Check that a program made of these stages can link.
+  /// Returns a description of the problem if it can't.
+  pub fn validate_combination(stages: &[ShaderStage]) -> Result<(), String> {
+    let has = |stage| stages.iter().any(|&s| s == stage);
+
+    if stages.is_empty() {
+      return Err(String::from("a program needs at least one shader stage"));
+    }
+    if has(ShaderStage::Compute) && stages.iter().any(|&s| s != ShaderStage::Compute) {
+      return Err(String::from("compute shaders can't be linked with other stages"));
+    }
+    if has(ShaderStage::TessControl) && !has(ShaderStage::TessEvaluation) {
+      return Err(String::from("a tessellation control shader needs a tessellation evaluation shader"));
+    }
+    for &stage in &[ShaderStage::TessControl, ShaderStage::TessEvaluation, ShaderStage::Geometry] {
+      if has(stage) && !has(ShaderStage::Vertex) {
+        return Err(format!("a {} shader needs a vertex shader", stage));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Display for ShaderStage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
@@ -0,0 +1,198 @@
+use gl;
+use gl::types::*;
+use std;
+use std::fmt;
+use texture::TextureUnit;
+
+use super::reflect::GlslType;
+
+/// A value that can be uploaded as one element of a GLSL uniform.
+pub trait UniformElement: Sized {
+  /// Whether this can be assigned to a uniform of GLSL type `typ`.
+  fn matches(typ: GlslType) -> bool;
+
+  /// Upload `values` to consecutive array elements, starting at `location`.
+  /// The owning program must be in use.
+  unsafe fn upload_array(location: GLint, values: &[Self]);
+}
+
+/// A value that can be passed to `Shader::set_uniform`:
+/// any `UniformElement`, or a slice of them for array uniforms.
+pub trait Uniform {
+  /// Whether this can be assigned to a uniform of GLSL type `typ`.
+  fn accepts(&self, typ: GlslType) -> bool;
+
+  /// Number of array elements this value covers.
+  fn count(&self) -> usize;
+
+  /// The owning program must be in use.
+  unsafe fn upload(&self, location: GLint);
+}
+
+impl<T: UniformElement> Uniform for T {
+  fn accepts(&self, typ: GlslType) -> bool {
+    <T as UniformElement>::matches(typ)
+  }
+
+  fn count(&self) -> usize {
+    1
+  }
+
+  unsafe fn upload(&self, location: GLint) {
+    T::upload_array(location, std::slice::from_ref(self));
+  }
+}
+
+impl<T: UniformElement> Uniform for [T] {
+  fn accepts(&self, typ: GlslType) -> bool {
+    <T as UniformElement>::matches(typ)
+  }
+
+  fn count(&self) -> usize {
+    self.len()
+  }
+
+  unsafe fn upload(&self, location: GLint) {
+    T::upload_array(location, self);
+  }
+}
+
+macro_rules! vector_uniform {
+  ($t:ty, $component:ty, $upload:ident, $($glsl:ident)|+) => {
+    impl UniformElement for $t {
+      fn matches(typ: GlslType) -> bool {
+        match typ {
+          $(GlslType::$glsl)|+ => true,
+          _ => false,
+        }
+      }
+
+      unsafe fn upload_array(location: GLint, values: &[$t]) {
+        gl::$upload(location, values.len() as GLsizei, values.as_ptr() as *const $component);
+      }
+    }
+  }
+}
+
+macro_rules! matrix_uniform {
+  ($t:ty, $upload:ident, $glsl:ident) => {
+    impl UniformElement for $t {
+      fn matches(typ: GlslType) -> bool {
+        typ == GlslType::$glsl
+      }
+
+      unsafe fn upload_array(location: GLint, values: &[$t]) {
+        gl::$upload(location, values.len() as GLsizei, gl::FALSE as GLboolean, values.as_ptr() as *const GLfloat);
+      }
+    }
+  }
+}
+
+vector_uniform!(f32     , GLfloat , Uniform1fv, Float);
+vector_uniform!([f32; 2], GLfloat , Uniform2fv, Vec2);
+vector_uniform!([f32; 3], GLfloat , Uniform3fv, Vec3);
+vector_uniform!([f32; 4], GLfloat , Uniform4fv, Vec4);
+vector_uniform!(f64     , GLdouble, Uniform1dv, Double);
+vector_uniform!([f64; 2], GLdouble, Uniform2dv, DVec2);
+vector_uniform!([f64; 3], GLdouble, Uniform3dv, DVec3);
+vector_uniform!([f64; 4], GLdouble, Uniform4dv, DVec4);
+vector_uniform!([i32; 2], GLint   , Uniform2iv, IVec2 | BVec2);
+vector_uniform!([i32; 3], GLint   , Uniform3iv, IVec3 | BVec3);
+vector_uniform!([i32; 4], GLint   , Uniform4iv, IVec4 | BVec4);
+vector_uniform!(u32     , GLuint  , Uniform1uiv, UInt | Bool);
+vector_uniform!([u32; 2], GLuint  , Uniform2uiv, UVec2 | BVec2);
+vector_uniform!([u32; 3], GLuint  , Uniform3uiv, UVec3 | BVec3);
+vector_uniform!([u32; 4], GLuint  , Uniform4uiv, UVec4 | BVec4);
+
+// Matrices are arrays of columns.
+matrix_uniform!([[f32; 2]; 2], UniformMatrix2fv  , Mat2);
+matrix_uniform!([[f32; 3]; 3], UniformMatrix3fv  , Mat3);
+matrix_uniform!([[f32; 4]; 4], UniformMatrix4fv  , Mat4);
+matrix_uniform!([[f32; 3]; 2], UniformMatrix2x3fv, Mat2x3);
+matrix_uniform!([[f32; 4]; 2], UniformMatrix2x4fv, Mat2x4);
+matrix_uniform!([[f32; 2]; 3], UniformMatrix3x2fv, Mat3x2);
+matrix_uniform!([[f32; 4]; 3], UniformMatrix3x4fv, Mat3x4);
+matrix_uniform!([[f32; 2]; 4], UniformMatrix4x2fv, Mat4x2);
+matrix_uniform!([[f32; 3]; 4], UniformMatrix4x3fv, Mat4x3);
+
+/// `int`s can also set `bool`s and samplers.
+impl UniformElement for i32 {
+  fn matches(typ: GlslType) -> bool {
+    typ == GlslType::Int || typ == GlslType::Bool || typ.is_sampler()
+  }
+
+  unsafe fn upload_array(location: GLint, values: &[i32]) {
+    gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr());
+  }
+}
+
+impl UniformElement for bool {
+  fn matches(typ: GlslType) -> bool {
+    typ == GlslType::Bool
+  }
+
+  unsafe fn upload_array(location: GLint, values: &[bool]) {
+    let values: Vec<GLint> = values.iter().map(|&b| b as GLint).collect();
+    gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr());
+  }
+}
+
+/// Points a sampler at a texture unit.
+impl UniformElement for TextureUnit {
+  fn matches(typ: GlslType) -> bool {
+    typ.is_sampler()
+  }
+
+  unsafe fn upload_array(location: GLint, values: &[TextureUnit]) {
+    let values: Vec<GLint> = values.iter().map(|u| u.glsl_id as GLint).collect();
+    gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr());
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniformError {
+  /// There's no active uniform with this name. It may have been optimized out.
+  NotFound(String),
+  /// The value's type doesn't match the uniform's declared type.
+  TypeMismatch {
+    name: String,
+    declared: GlslType,
+  },
+  /// More array elements were passed than the uniform has.
+  TooManyElements {
+    name: String,
+    size: u32,
+    given: usize,
+  },
+  /// There's no active uniform block with this name.
+  BlockNotFound(String),
+  /// The name contains a NUL byte, so it can't be passed to GL.
+  InvalidName(String),
+  /// The buffer is smaller than the uniform block it's bound to.
+  BlockTooSmall {
+    name: String,
+    required: usize,
+    given: usize,
+  },
+}
+
+impl fmt::Display for UniformError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      UniformError::NotFound(ref name) =>
+        write!(f, "couldn't find shader uniform: {}", name),
+      UniformError::TypeMismatch { ref name, declared } =>
+        write!(f, "uniform {} is a {}, which doesn't match the value passed", name, declared),
+      UniformError::TooManyElements { ref name, size, given } =>
+        write!(f, "uniform {} has {} elements, but {} were passed", name, size, given),
+      UniformError::BlockNotFound(ref name) =>
+        write!(f, "couldn't find uniform block: {}", name),
+      UniformError::InvalidName(ref name) =>
+        write!(f, "name contains a NUL byte: {:?}", name),
+      UniformError::BlockTooSmall { ref name, required, given } =>
+        write!(f, "uniform block {} needs {} bytes, but the buffer has {}", name, required, given),
+    }
+  }
+}
+
+impl std::error::Error for UniformError {}
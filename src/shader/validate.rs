@@ -0,0 +1,165 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std;
+use std::ffi::CString;
+use std::fmt;
+use std::fmt::Write;
+
+use super::{Shader, program_info_log};
+use super::error::{Diagnostic, parse_log};
+use super::reflect::UniformInfo;
+
+/// `glValidateProgram` said the program can run with the current GL state.
+/// Drivers may still have left warnings or performance notes in the log.
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+  /// The info log, exactly as the driver returned it. Often empty.
+  pub log: String,
+  /// `log`, parsed line by line. Lines that couldn't be parsed are left out.
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+/// `glValidateProgram` said the program can't run with the current GL state.
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+  /// The info log, exactly as the driver returned it.
+  pub log: String,
+  /// `log`, parsed line by line. Lines that couldn't be parsed are left out.
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "shader program failed validation:")?;
+
+    if self.diagnostics.is_empty() {
+      return write!(f, "\n{}", self.log.trim_end());
+    }
+
+    for diagnostic in &self.diagnostics {
+      write!(f, "\n{}", diagnostic)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl<'a> Shader<'a> {
+  /// Check whether this program can run with the current GL state
+  /// (e.g. that no two samplers of different types share a texture unit).
+  /// Drivers do most of their checking at draw time, so validate right before a suspect draw call.
+  pub fn validate(&self, _gl: &GLContext) -> Result<ValidationReport, ValidationError> {
+    let mut status = gl::FALSE as GLint;
+    unsafe {
+      gl::ValidateProgram(self.handle.gl_id);
+      gl::GetProgramiv(self.handle.gl_id, gl::VALIDATE_STATUS, &mut status);
+    }
+
+    let log = program_info_log(self.handle.gl_id);
+    if status == (gl::TRUE as GLint) {
+      return Ok(ValidationReport {
+        diagnostics: parse_log(&log),
+        log: log,
+      });
+    }
+
+    Err(ValidationError {
+      diagnostics: parse_log(&log),
+      log: log,
+    })
+  }
+
+  /// The texture unit each element of a sampler uniform reads from.
+  fn sampler_units(&self, uniform: &UniformInfo) -> Vec<GLint> {
+    let base =
+      if uniform.name.ends_with("[0]") {
+        &uniform.name[.. uniform.name.len() - 3]
+      } else {
+        &uniform.name[..]
+      };
+
+    (0 .. uniform.size).filter_map(|i| {
+      let name = if uniform.size > 1 { format!("{}[{}]", base, i) } else { uniform.name.clone() };
+      let c_name = CString::new(name.as_bytes()).unwrap();
+      let location = unsafe {
+        gl::GetUniformLocation(self.handle.gl_id, c_name.as_ptr() as *const GLchar)
+      };
+      if location == -1 {
+        return None;
+      }
+      let mut unit = 0;
+      unsafe {
+        gl::GetUniformiv(self.handle.gl_id, location, &mut unit);
+      }
+      Some(unit)
+    }).collect()
+  }
+
+  /// A human-readable report of the program: its stages, active attributes, uniforms
+  /// (with the texture unit each sampler reads from) and uniform blocks.
+  pub fn describe(&self) -> String {
+    let mut s = String::new();
+    // Writing to a `String` can't fail.
+    let _ = self.describe_into(&mut s);
+    s
+  }
+
+  fn describe_into(&self, s: &mut String) -> fmt::Result {
+    writeln!(s, "program {}", self.handle.gl_id)?;
+
+    if self.components.is_empty() {
+      writeln!(s, "  stages: unknown (loaded from a program binary)")?;
+    } else {
+      let stages: Vec<String> =
+        self.components.iter().map(|c| format!("{} (shader {})", c.stage, c.gl_id)).collect();
+      writeln!(s, "  stages: {}", stages.join(", "))?;
+    }
+
+    writeln!(s, "  attributes:")?;
+    for attribute in &self.info.attributes {
+      write!(s, "    {} {}", attribute.typ, attribute.name)?;
+      if attribute.size > 1 {
+        write!(s, " [{}]", attribute.size)?;
+      }
+      match attribute.location {
+        Some(location) => writeln!(s, " @ location {}", location)?,
+        None => writeln!(s, " (built-in)")?,
+      }
+    }
+
+    writeln!(s, "  uniforms:")?;
+    for uniform in self.info.uniforms.iter().filter(|u| u.block_index.is_none()) {
+      write!(s, "    {} {}", uniform.typ, uniform.name)?;
+      if uniform.size > 1 {
+        write!(s, " [{}]", uniform.size)?;
+      }
+      if let Some(location) = uniform.location {
+        write!(s, " @ location {}", location)?;
+      }
+      if uniform.typ.is_sampler() {
+        let units: Vec<String> = self.sampler_units(uniform).iter().map(|u| u.to_string()).collect();
+        write!(s, " -> texture unit {}", units.join(", "))?;
+      }
+      writeln!(s)?;
+    }
+
+    writeln!(s, "  uniform blocks:")?;
+    for block in &self.info.uniform_blocks {
+      writeln!(s, "    {} ({} bytes) @ binding {}", block.name, block.data_size, block.binding)?;
+      for member in self.info.block_members(block) {
+        write!(s, "      {} {}", member.typ, member.name)?;
+        if member.size > 1 {
+          write!(s, " [{}]", member.size)?;
+        }
+        match member.offset {
+          Some(offset) => writeln!(s, " @ offset {}", offset)?,
+          None => writeln!(s)?,
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,150 @@
+use gl_context::GLContext;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use super::Shader;
+use super::error::ShaderError;
+use super::include::{directive, line_directive_offset};
+use super::stage::ShaderStage;
+
+/// A set of `#define`s. Ordered, so that equal sets compare and hash equally.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Defines {
+  pub defines: BTreeMap<String, String>,
+}
+
+impl Defines {
+  pub fn new() -> Defines {
+    Defines {
+      defines: BTreeMap::new(),
+    }
+  }
+
+  /// `#define name`
+  pub fn flag(mut self, name: &str) -> Defines {
+    self.defines.insert(String::from(name), String::new());
+    self
+  }
+
+  /// `#define name value`
+  pub fn value(mut self, name: &str, value: &str) -> Defines {
+    self.defines.insert(String::from(name), String::from(value));
+    self
+  }
+
+  /// Insert these `#define`s into `source`, right after its `#version` line.
+  /// A `#line` directive keeps the line numbers in compile errors pointing at the original source.
+  pub fn inject(&self, source: &str) -> String {
+    if self.defines.is_empty() {
+      return String::from(source);
+    }
+
+    let mut defines = String::new();
+    for (name, value) in &self.defines {
+      if value.is_empty() {
+        defines.push_str(&format!("#define {}\n", name));
+      } else {
+        defines.push_str(&format!("#define {} {}\n", name, value));
+      }
+    }
+
+    let version_line =
+      source
+        .lines()
+        .position(|line| directive(line).map_or(false, |(name, _)| name == "version"));
+    let insert_after = version_line.map_or(0, |i| i + 1);
+
+    let mut output = String::new();
+    for (i, line) in source.lines().enumerate() {
+      if i == insert_after {
+        output.push_str(&defines);
+        output.push_str(&line_directive(source, insert_after as u32 + 1));
+      }
+      output.push_str(line);
+      output.push('\n');
+    }
+    if insert_after >= source.lines().count() {
+      output.push_str(&defines);
+    }
+
+    output
+  }
+}
+
+fn line_directive(source: &str, next_line: u32) -> String {
+  format!("#line {}\n", next_line - line_directive_offset(source))
+}
+
+/// Compiles permutations of the same sources with different `#define`s,
+/// and keeps the linked programs around so each permutation is only built once.
+pub struct ShaderVariants<'a> {
+  pub components: Vec<(ShaderStage, String)>,
+  pub programs: HashMap<Defines, Shader<'a>>,
+}
+
+impl<'a> ShaderVariants<'a> {
+  pub fn new(components: Vec<(ShaderStage, String)>) -> ShaderVariants<'a> {
+    ShaderVariants {
+      components: components,
+      programs: HashMap::new(),
+    }
+  }
+
+  /// Fetch the program for this set of `#define`s, compiling and linking it if it isn't cached yet.
+  pub fn get(&mut self, gl: &GLContext, defines: &Defines) -> Result<&mut Shader<'a>, ShaderError> {
+    if !self.programs.contains_key(defines) {
+      let shader =
+        Shader::new(
+          gl,
+          self.components.iter().map(|&(stage, ref source)| (stage, defines.inject(source))),
+        )?;
+      self.programs.insert(defines.clone(), shader);
+    }
+
+    Ok(self.programs.get_mut(defines).unwrap())
+  }
+
+  /// Drop all the cached programs.
+  pub fn clear(&mut self) {
+    self.programs.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn after_version() {
+    let defines = Defines::new().flag("SHADOWS").value("LIGHTS", "4");
+    assert_eq!(
+      defines.inject("#version 330\nvoid main() {}\n"),
+      "#version 330\n#define LIGHTS 4\n#define SHADOWS\n#line 2\nvoid main() {}\n",
+    );
+  }
+
+  #[test]
+  fn version_not_on_first_line() {
+    let defines = Defines::new().flag("SHADOWS");
+    assert_eq!(
+      defines.inject("// lighting\n#version 330\nvoid main() {}\n"),
+      "// lighting\n#version 330\n#define SHADOWS\n#line 3\nvoid main() {}\n",
+    );
+  }
+
+  #[test]
+  fn no_version() {
+    // No `#version` means GLSL 1.10, where `#line n` numbers the next line `n + 1`.
+    let defines = Defines::new().flag("SHADOWS");
+    assert_eq!(
+      defines.inject("void main() {}\n"),
+      "#define SHADOWS\n#line 0\nvoid main() {}\n",
+    );
+  }
+
+  #[test]
+  fn no_defines() {
+    let source = "#version 330\nvoid main() {}\n";
+    assert_eq!(Defines::new().inject(source), source);
+  }
+}
@@ -0,0 +1,318 @@
+use gl;
+use gl::types::*;
+use gl_context::GLContext;
+use std::iter::repeat;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+
+use shader::{Shader, UniformError};
+use uniform_buffer::{BufferError, Field, LayoutError, check_fields, check_struct, round_up, struct_end, struct_layout};
+use vertex_buffer::GLByteBuffer;
+
+/// A type whose Rust layout can be copied directly into a std430 shader storage block.
+///
+/// Implemented for scalars, vectors (`[f32; 3]` etc.), `mat2`, and column-major matrices whose columns are `vec4`s.
+/// Implement it for `#[repr(C)]` structs with `impl_std430!`.
+pub unsafe trait Std430: Copy {
+  /// Base alignment, in bytes.
+  fn std430_align() -> usize;
+
+  /// Size, in bytes.
+  fn std430_size() -> usize;
+
+  /// Check that the Rust layout of this type matches std430.
+  fn check_std430() -> Result<(), LayoutError> {
+    Ok(())
+  }
+
+  /// Where the last member ends, before any padding out to the alignment.
+  /// When this type's members are followed by a runtime-sized array in a block,
+  /// the array starts here, rounded up to its own alignment.
+  fn std430_end() -> usize {
+    Self::std430_size()
+  }
+
+  /// Like `check_std430`, but for a type whose members are flattened into a block
+  /// ahead of a runtime-sized array, so it doesn't need padding after its last member.
+  fn check_std430_header() -> Result<(), LayoutError> {
+    Self::check_std430()
+  }
+}
+
+macro_rules! std430_primitive {
+  ($t:ty, $align:expr) => {
+    unsafe impl Std430 for $t {
+      fn std430_align() -> usize {
+        $align
+      }
+
+      fn std430_size() -> usize {
+        mem::size_of::<$t>()
+      }
+    }
+  }
+}
+
+// An empty header, for buffers that are just a runtime-sized array.
+std430_primitive!((), 1);
+std430_primitive!(f32, 4);
+std430_primitive!(i32, 4);
+std430_primitive!(u32, 4);
+std430_primitive!([f32; 2], 8);
+std430_primitive!([i32; 2], 8);
+std430_primitive!([u32; 2], 8);
+std430_primitive!([f32; 3], 16);
+std430_primitive!([i32; 3], 16);
+std430_primitive!([u32; 3], 16);
+std430_primitive!([f32; 4], 16);
+std430_primitive!([i32; 4], 16);
+std430_primitive!([u32; 4], 16);
+std430_primitive!([[f32; 2]; 2], 8);
+std430_primitive!([[f32; 4]; 2], 16);
+std430_primitive!([[f32; 4]; 3], 16);
+std430_primitive!([[f32; 4]; 4], 16);
+
+impl Field {
+  /// Like `Field::of`, with std430 rules.
+  pub fn of_std430<F: Std430>(name: &'static str, base: *const u8, field: *const F) -> Field {
+    Field {
+      name: name,
+      offset: field as usize - base as usize,
+      align: F::std430_align(),
+      size: F::std430_size(),
+      check: F::check_std430(),
+    }
+  }
+}
+
+pub fn std430_struct_layout(fields: &[Field]) -> (usize, usize) {
+  struct_layout(fields, 1)
+}
+
+pub fn check_std430_struct(fields: &[Field], size: usize) -> Result<(), LayoutError> {
+  check_struct(fields, size, 1)
+}
+
+/// Check that `fields` are where std430 would put them, and that a `size`-byte struct holds them all.
+pub fn check_std430_header(fields: &[Field], size: usize) -> Result<(), LayoutError> {
+  check_fields(fields)?;
+
+  let end = struct_end(fields);
+  if size < end {
+    return Err(LayoutError::WrongSize {
+      expected_size: end,
+      size: size,
+    });
+  }
+
+  Ok(())
+}
+
+/// Implement `Std430` for a `#[repr(C)]` struct.
+/// List the fields that correspond to GLSL block members, in order; leave out padding fields.
+#[macro_export]
+macro_rules! impl_std430 {
+  ($t:ty { $($field:ident),* $(,)* }) => {
+    impl $t {
+      #[allow(dead_code)]
+      fn std430_fields() -> Vec<$crate::uniform_buffer::Field> {
+        let value = ::std::mem::MaybeUninit::<$t>::uninit();
+        let base = value.as_ptr();
+        vec!($(
+          $crate::uniform_buffer::Field::of_std430(
+            stringify!($field),
+            base as *const u8,
+            unsafe { ::std::ptr::addr_of!((*base).$field) },
+          )
+        ),*)
+      }
+    }
+
+    unsafe impl $crate::storage_buffer::Std430 for $t {
+      fn std430_align() -> usize {
+        $crate::storage_buffer::std430_struct_layout(&<$t>::std430_fields()).0
+      }
+
+      fn std430_size() -> usize {
+        $crate::storage_buffer::std430_struct_layout(&<$t>::std430_fields()).1
+      }
+
+      fn check_std430() -> Result<(), $crate::uniform_buffer::LayoutError> {
+        $crate::storage_buffer::check_std430_struct(&<$t>::std430_fields(), ::std::mem::size_of::<$t>())
+      }
+
+      fn std430_end() -> usize {
+        $crate::uniform_buffer::struct_end(&<$t>::std430_fields())
+      }
+
+      fn check_std430_header() -> Result<(), $crate::uniform_buffer::LayoutError> {
+        $crate::storage_buffer::check_std430_header(&<$t>::std430_fields(), ::std::mem::size_of::<$t>())
+      }
+    }
+  }
+}
+
+/// A shader storage buffer: a fixed `H` header, followed by a runtime-sized array of `T`s.
+/// Corresponds to a GLSL block like `buffer B { H's members; T items[]; }`.
+/// Since `H`'s members are flattened into the block, the array starts right after the last of them,
+/// not after `H`'s padding.
+pub struct ShaderStorageBuffer<'a, T, H = ()> {
+  pub byte_buffer: GLByteBuffer<'a>,
+  /// Byte offset of the array, after the header.
+  pub array_offset: usize,
+  /// length in `T`s.
+  pub length: usize,
+  phantom: PhantomData<(H, Vec<T>)>,
+}
+
+impl<'a, T: Std430, H: Std430> ShaderStorageBuffer<'a, T, H> {
+  /// Allocate room for `header` and `capacity` `T`s.
+  /// Fails with `BufferError::Layout` if `T` or `H` don't match std430.
+  pub fn new<'b:'a>(
+    gl: &'a mut GLContext,
+    header: &H,
+    capacity: usize,
+  ) -> Result<ShaderStorageBuffer<'b, T, H>, BufferError> {
+    H::check_std430_header()?;
+    T::check_std430()?;
+    // Array elements are padded out to their alignment.
+    let stride = round_up(T::std430_size(), T::std430_align());
+    if stride != mem::size_of::<T>() {
+      return Err(BufferError::Layout(
+        LayoutError::WrongSize { expected_size: stride, size: mem::size_of::<T>() },
+      ));
+    }
+
+    let array_offset = round_up(H::std430_end(), T::std430_align());
+    let mut byte_buffer =
+      GLByteBuffer::with_target(
+        gl,
+        gl::SHADER_STORAGE_BUFFER,
+        array_offset + capacity * mem::size_of::<T>(),
+      )?;
+    unsafe {
+      byte_buffer.push(gl, header as *const H as *const u8, H::std430_end());
+    }
+    byte_buffer.length = array_offset;
+
+    Ok(ShaderStorageBuffer {
+      byte_buffer: byte_buffer,
+      array_offset: array_offset,
+      length: 0,
+      phantom: PhantomData,
+    })
+  }
+
+  pub fn bind(&self, gl: &mut GLContext) {
+    self.byte_buffer.bind(gl);
+  }
+
+  /// Attach this buffer to an indexed shader storage binding point.
+  pub fn bind_base(&self, _gl: &mut GLContext, binding: GLuint) {
+    unsafe {
+      gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.byte_buffer.handle.gl_id);
+    }
+  }
+
+  /// Point `shader`'s storage block `block_name` at `binding`, and attach this buffer there.
+  pub fn bind_to_block(
+    &self,
+    gl: &mut GLContext,
+    shader: &mut Shader,
+    block_name: &str,
+    binding: GLuint,
+  ) -> Result<(), UniformError> {
+    shader.bind_storage_block(gl, block_name, binding)?;
+    self.bind_base(gl, binding);
+    Ok(())
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn update_header(&mut self, gl: &mut GLContext, header: &H) {
+    unsafe {
+      self.byte_buffer.update(gl, 0, header as *const H as *const u8, H::std430_end());
+    }
+  }
+
+  /// Returns false and does nothing if this would exceed the capacity of the buffer.
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn push(&mut self, gl: &mut GLContext, vs: &[T]) -> bool {
+    let r = unsafe {
+      self.byte_buffer.push(gl, vs.as_ptr() as *const u8, mem::size_of::<T>() * vs.len())
+    };
+    if r {
+      self.length += vs.len();
+    }
+    r
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn update(&mut self, gl: &mut GLContext, idx: usize, vs: &[T]) {
+    unsafe {
+      self.byte_buffer.update(
+        gl,
+        self.array_offset + mem::size_of::<T>() * idx,
+        vs.as_ptr() as *const u8,
+        mem::size_of::<T>() * vs.len(),
+      );
+    }
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn swap_remove(&mut self, gl: &mut GLContext, idx: usize, count: usize) {
+    self.byte_buffer.swap_remove(
+      gl,
+      self.array_offset + mem::size_of::<T>() * idx,
+      mem::size_of::<T>() * count,
+    );
+    self.length -= count;
+  }
+
+  /// Read the header back from the GPU.
+  /// Writes from shaders are only visible after an appropriate `glMemoryBarrier`.
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn read_header(&self, _gl: &mut GLContext) -> H {
+    unsafe {
+      let mut header: H = mem::zeroed();
+      gl::GetBufferSubData(
+        gl::SHADER_STORAGE_BUFFER,
+        0,
+        H::std430_end() as GLsizeiptr,
+        &mut header as *mut H as *mut c_void,
+      );
+      header
+    }
+  }
+
+  /// Read `len` array elements, starting at `start`, back from the GPU.
+  /// Writes from shaders are only visible after an appropriate `glMemoryBarrier`.
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn read_slice(&self, _gl: &mut GLContext, start: usize, len: usize) -> Vec<T> {
+    assert!(start + len <= self.length);
+
+    unsafe {
+      let mut vs: Vec<T> = repeat(mem::zeroed()).take(len).collect();
+      gl::GetBufferSubData(
+        gl::SHADER_STORAGE_BUFFER,
+        (self.array_offset + start * mem::size_of::<T>()) as GLintptr,
+        (len * mem::size_of::<T>()) as GLsizeiptr,
+        vs.as_mut_ptr() as *mut c_void,
+      );
+      vs
+    }
+  }
+
+  /// Read the whole array back from the GPU.
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn read(&self, gl: &mut GLContext) -> Vec<T> {
+    self.read_slice(gl, 0, self.length)
+  }
+}
@@ -1,6 +1,6 @@
 use gl;
 use gl::types::*;
-use gl_context::GLContext;
+use gl_context::{GLContext, GlError};
 use std::default::Default;
 use std::marker::PhantomData;
 use std::ops::Add;
@@ -71,10 +71,12 @@ pub struct Texture2D<'a> {
 }
 
 impl<'a> Texture2D<'a> {
-  pub fn new<'b:'a>(gl: &'a GLContext) -> Texture2D<'b> {
-    Texture2D {
-      handle: TextureHandle::new(gl),
-    }
+  pub fn new<'b:'a>(gl: &'a GLContext) -> Result<Texture2D<'b>, GlError> {
+    let handle = TextureHandle::new(gl);
+    gl.check_errors()?;
+    Ok(Texture2D {
+      handle: handle,
+    })
   }
 }
 
@@ -89,10 +91,10 @@ impl<'a, T> BufferTexture<'a, T> {
     gl: &'a mut GLContext,
     format: GLenum,
     capacity: usize,
-  ) -> BufferTexture<'b, T> {
+  ) -> Result<BufferTexture<'b, T>, GlError> {
     // TODO: enforce that `format` matches T.
 
-    let buffer = GLBuffer::new(gl, capacity);
+    let buffer = GLBuffer::new(gl, capacity)?;
     let handle = TextureHandle::new(gl);
 
     unsafe {
@@ -100,9 +102,11 @@ impl<'a, T> BufferTexture<'a, T> {
       gl::TexBuffer(gl::TEXTURE_BUFFER, format, buffer.byte_buffer.handle.gl_id);
     }
 
-    BufferTexture {
+    gl.check_errors()?;
+
+    Ok(BufferTexture {
       handle: handle,
       buffer: buffer,
-    }
+    })
   }
 }
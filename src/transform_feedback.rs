@@ -0,0 +1,144 @@
+use gl;
+use gl::types::*;
+use gl_context::{GLContext, GlError};
+use std::marker::PhantomData;
+
+use vertex_buffer::{DrawMode, GLByteBuffer};
+
+/// How captured varyings are laid out in the transform feedback buffers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FeedbackBufferMode {
+  /// All varyings are written, one vertex after another, into a single buffer.
+  Interleaved,
+  /// Each varying is written into its own buffer, bound at the varying's index.
+  Separate,
+}
+
+impl FeedbackBufferMode {
+  pub fn gl_enum(&self) -> GLenum {
+    match *self {
+      FeedbackBufferMode::Interleaved => gl::INTERLEAVED_ATTRIBS,
+      FeedbackBufferMode::Separate    => gl::SEPARATE_ATTRIBS,
+    }
+  }
+}
+
+/// A transform feedback object: captures the output of the last vertex processing stage into buffers.
+/// The program must have been linked with `Shader::with_transform_feedback`.
+pub struct TransformFeedback<'a> {
+  pub gl_id: GLuint,
+  /// A `GL_TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN` query, active between `begin` and `end`.
+  pub query_id: GLuint,
+  /// The primitive mode of the last `begin`.
+  mode: Option<DrawMode>,
+  phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> TransformFeedback<'a> {
+  pub fn new<'b:'a>(gl: &'a GLContext) -> Result<TransformFeedback<'b>, GlError> {
+    let mut gl_id = 0;
+    let mut query_id = 0;
+    unsafe {
+      gl::GenTransformFeedbacks(1, &mut gl_id);
+      gl::GenQueries(1, &mut query_id);
+    }
+
+    let feedback = TransformFeedback {
+      gl_id: gl_id,
+      query_id: query_id,
+      mode: None,
+      phantom: PhantomData,
+    };
+
+    gl.check_errors()?;
+
+    Ok(feedback)
+  }
+
+  pub fn bind(&self, _gl: &mut GLContext) {
+    unsafe {
+      gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.gl_id);
+    }
+  }
+
+  /// Capture into `buffer`, at binding `index`. With `FeedbackBufferMode::Interleaved`, only index 0 is used.
+  /// The buffer mustn't be read as a vertex source while it's being captured into.
+  /// N.B. For performance reasons, this does NOT bind the transform feedback object.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn bind_buffer(&mut self, _gl: &mut GLContext, index: GLuint, buffer: &GLByteBuffer) {
+    unsafe {
+      gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index, buffer.handle.gl_id);
+    }
+  }
+
+  /// Start capturing. Draw calls until `end` must draw `mode` primitives.
+  /// Also starts counting the primitives written.
+  /// N.B. For performance reasons, this does NOT bind the transform feedback object.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn begin(&mut self, _gl: &mut GLContext, mode: DrawMode) {
+    unsafe {
+      gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, self.query_id);
+      gl::BeginTransformFeedback(mode.to_enum());
+    }
+    self.mode = Some(mode);
+  }
+
+  /// Temporarily stop capturing, e.g. to draw something else with a different program.
+  pub fn pause(&mut self, _gl: &mut GLContext) {
+    unsafe {
+      gl::PauseTransformFeedback();
+    }
+  }
+
+  pub fn resume(&mut self, _gl: &mut GLContext) {
+    unsafe {
+      gl::ResumeTransformFeedback();
+    }
+  }
+
+  pub fn end(&mut self, _gl: &mut GLContext) {
+    unsafe {
+      gl::EndTransformFeedback();
+      gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+    }
+  }
+
+  /// The number of primitives written between the last `begin` and `end`.
+  /// This waits for the GPU to finish capturing.
+  pub fn primitives_written(&self, _gl: &mut GLContext) -> u32 {
+    let mut written = 0;
+    unsafe {
+      gl::GetQueryObjectuiv(self.query_id, gl::QUERY_RESULT, &mut written);
+    }
+    written
+  }
+
+  /// Like `primitives_written`, but returns `None` instead of waiting if the result isn't ready yet.
+  pub fn try_primitives_written(&self, gl: &mut GLContext) -> Option<u32> {
+    let mut available = 0;
+    unsafe {
+      gl::GetQueryObjectuiv(self.query_id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+    }
+    if available == 0 {
+      None
+    } else {
+      Some(self.primitives_written(gl))
+    }
+  }
+
+  /// The number of vertices written between the last `begin` and `end`.
+  /// Pass this to `GLArray::set_length` to draw what was captured. Waits like `primitives_written`.
+  pub fn vertices_written(&self, gl: &mut GLContext) -> usize {
+    let per_primitive = self.mode.map(|m| m.vertices_per_primitive()).unwrap_or(0);
+    self.primitives_written(gl) as usize * per_primitive
+  }
+}
+
+impl<'a> Drop for TransformFeedback<'a> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteQueries(1, &self.query_id);
+      gl::DeleteTransformFeedbacks(1, &self.gl_id);
+    }
+  }
+}
@@ -0,0 +1,318 @@
+use gl;
+use gl::types::*;
+use gl_context::{GLContext, GlError};
+use std;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+
+use shader::{Shader, UniformError};
+use vertex_buffer::GLByteBuffer;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+  /// A field isn't where the layout rules would put it. Add or remove explicit padding before it.
+  Misplaced {
+    field: &'static str,
+    expected_offset: usize,
+    offset: usize,
+  },
+  /// The struct's size doesn't match the layout rules. Add or remove explicit padding at the end.
+  WrongSize {
+    expected_size: usize,
+    size: usize,
+  },
+}
+
+impl fmt::Display for LayoutError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      LayoutError::Misplaced { field, expected_offset, offset } =>
+        write!(f, "field {} is at byte {}, but should be at byte {}", field, offset, expected_offset),
+      LayoutError::WrongSize { expected_size, size } =>
+        write!(f, "struct is {} bytes, but should be {} bytes", size, expected_size),
+    }
+  }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Why a typed buffer couldn't be created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BufferError {
+  /// The Rust type doesn't match the GLSL layout rules.
+  Layout(LayoutError),
+  Gl(GlError),
+}
+
+impl fmt::Display for BufferError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      BufferError::Layout(ref err) => write!(f, "buffer type doesn't match its layout: {}", err),
+      BufferError::Gl(ref err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl std::error::Error for BufferError {}
+
+impl From<LayoutError> for BufferError {
+  fn from(err: LayoutError) -> BufferError {
+    BufferError::Layout(err)
+  }
+}
+
+impl From<GlError> for BufferError {
+  fn from(err: GlError) -> BufferError {
+    BufferError::Gl(err)
+  }
+}
+
+pub fn round_up(x: usize, align: usize) -> usize {
+  (x + align - 1) / align * align
+}
+
+/// A type whose Rust layout can be copied directly into a std140 uniform block.
+///
+/// Implemented for scalars, vectors (`[f32; 3]` etc.) and column-major matrices whose columns are `vec4`s.
+/// Implement it for `#[repr(C)]` structs with `impl_std140!`. Since `vec3`s are 16-byte aligned
+/// (and `mat3` columns are `vec4`s), structs usually need explicit padding fields.
+pub unsafe trait Std140: Copy {
+  /// Base alignment, in bytes.
+  fn std140_align() -> usize;
+
+  /// Size, in bytes.
+  fn std140_size() -> usize;
+
+  /// Check that the Rust layout of this type matches std140.
+  fn check_std140() -> Result<(), LayoutError> {
+    Ok(())
+  }
+}
+
+macro_rules! std140_primitive {
+  ($t:ty, $align:expr) => {
+    unsafe impl Std140 for $t {
+      fn std140_align() -> usize {
+        $align
+      }
+
+      fn std140_size() -> usize {
+        mem::size_of::<$t>()
+      }
+    }
+  }
+}
+
+std140_primitive!(f32, 4);
+std140_primitive!(i32, 4);
+std140_primitive!(u32, 4);
+std140_primitive!([f32; 2], 8);
+std140_primitive!([i32; 2], 8);
+std140_primitive!([u32; 2], 8);
+std140_primitive!([f32; 3], 16);
+std140_primitive!([i32; 3], 16);
+std140_primitive!([u32; 3], 16);
+std140_primitive!([f32; 4], 16);
+std140_primitive!([i32; 4], 16);
+std140_primitive!([u32; 4], 16);
+std140_primitive!([[f32; 4]; 2], 16);
+std140_primitive!([[f32; 4]; 3], 16);
+std140_primitive!([[f32; 4]; 4], 16);
+
+/// Where a struct field actually is, and what layout its type wants. Used by `impl_std140!` and `impl_std430!`.
+#[derive(Debug, Clone)]
+pub struct Field {
+  pub name: &'static str,
+  pub offset: usize,
+  pub align: usize,
+  pub size: usize,
+  pub check: Result<(), LayoutError>,
+}
+
+impl Field {
+  /// `field` points at the field inside the struct that starts at `base`. Neither is read.
+  pub fn of<F: Std140>(name: &'static str, base: *const u8, field: *const F) -> Field {
+    Field {
+      name: name,
+      offset: field as usize - base as usize,
+      align: F::std140_align(),
+      size: F::std140_size(),
+      check: F::check_std140(),
+    }
+  }
+}
+
+/// Where the last of these fields ends, before the struct is padded out to its alignment.
+pub fn struct_end(fields: &[Field]) -> usize {
+  let mut end = 0;
+  for field in fields {
+    end = round_up(end, field.align) + field.size;
+  }
+  end
+}
+
+/// The (alignment, size) of a struct with these fields.
+/// The struct's alignment is rounded up to `min_align` (16 for std140, 1 for std430).
+pub fn struct_layout(fields: &[Field], min_align: usize) -> (usize, usize) {
+  let align = round_up(fields.iter().map(|f| f.align).max().unwrap_or(1), min_align);
+  (align, round_up(struct_end(fields), align))
+}
+
+/// Check that `fields` are where the layout rules would put them.
+pub fn check_fields(fields: &[Field]) -> Result<(), LayoutError> {
+  let mut end = 0;
+  for field in fields {
+    field.check.clone()?;
+    let expected_offset = round_up(end, field.align);
+    if field.offset != expected_offset {
+      return Err(LayoutError::Misplaced {
+        field: field.name,
+        expected_offset: expected_offset,
+        offset: field.offset,
+      });
+    }
+    end = expected_offset + field.size;
+  }
+  Ok(())
+}
+
+/// Check that `fields` are where the layout rules would put them, and that the struct is `size` bytes.
+pub fn check_struct(fields: &[Field], size: usize, min_align: usize) -> Result<(), LayoutError> {
+  check_fields(fields)?;
+
+  let (_, expected_size) = struct_layout(fields, min_align);
+  if size != expected_size {
+    return Err(LayoutError::WrongSize {
+      expected_size: expected_size,
+      size: size,
+    });
+  }
+
+  Ok(())
+}
+
+pub fn std140_struct_layout(fields: &[Field]) -> (usize, usize) {
+  struct_layout(fields, 16)
+}
+
+pub fn check_std140_struct(fields: &[Field], size: usize) -> Result<(), LayoutError> {
+  check_struct(fields, size, 16)
+}
+
+/// Implement `Std140` for a `#[repr(C)]` struct.
+/// List the fields that correspond to GLSL block members, in order; leave out padding fields.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone)]
+/// struct Light {
+///   position: [f32; 3],
+///   intensity: f32,
+///   color: [f32; 3],
+///   _pad: f32,
+/// }
+/// impl_std140!(Light { position, intensity, color });
+/// ```
+#[macro_export]
+macro_rules! impl_std140 {
+  ($t:ty { $($field:ident),* $(,)* }) => {
+    impl $t {
+      #[allow(dead_code)]
+      fn std140_fields() -> Vec<$crate::uniform_buffer::Field> {
+        // Only the fields' addresses are needed, so the value is never initialized.
+        let value = ::std::mem::MaybeUninit::<$t>::uninit();
+        let base = value.as_ptr();
+        vec!($(
+          $crate::uniform_buffer::Field::of(
+            stringify!($field),
+            base as *const u8,
+            unsafe { ::std::ptr::addr_of!((*base).$field) },
+          )
+        ),*)
+      }
+    }
+
+    unsafe impl $crate::uniform_buffer::Std140 for $t {
+      fn std140_align() -> usize {
+        $crate::uniform_buffer::std140_struct_layout(&<$t>::std140_fields()).0
+      }
+
+      fn std140_size() -> usize {
+        $crate::uniform_buffer::std140_struct_layout(&<$t>::std140_fields()).1
+      }
+
+      fn check_std140() -> Result<(), $crate::uniform_buffer::LayoutError> {
+        $crate::uniform_buffer::check_std140_struct(&<$t>::std140_fields(), ::std::mem::size_of::<$t>())
+      }
+    }
+  }
+}
+
+/// A buffer holding one `T`, for backing a uniform block.
+pub struct UniformBuffer<'a, T> {
+  pub byte_buffer: GLByteBuffer<'a>,
+  phantom: PhantomData<T>,
+}
+
+impl<'a, T: Std140> UniformBuffer<'a, T> {
+  /// Fails with `BufferError::Layout` if `T`'s layout doesn't match std140.
+  pub fn new<'b:'a>(
+    gl: &'a mut GLContext,
+    value: &T,
+  ) -> Result<UniformBuffer<'b, T>, BufferError> {
+    T::check_std140()?;
+
+    let mut byte_buffer = GLByteBuffer::with_target(gl, gl::UNIFORM_BUFFER, mem::size_of::<T>())?;
+    unsafe {
+      byte_buffer.push(gl, value as *const T as *const u8, mem::size_of::<T>());
+    }
+
+    Ok(UniformBuffer {
+      byte_buffer: byte_buffer,
+      phantom: PhantomData,
+    })
+  }
+
+  pub fn bind(&self, gl: &mut GLContext) {
+    self.byte_buffer.bind(gl);
+  }
+
+  /// N.B. For performance reasons, this does NOT bind the buffer.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn update(&mut self, gl: &mut GLContext, value: &T) {
+    unsafe {
+      self.byte_buffer.update(gl, 0, value as *const T as *const u8, mem::size_of::<T>());
+    }
+  }
+
+  /// Attach this buffer to an indexed uniform buffer binding point.
+  pub fn bind_base(&self, _gl: &mut GLContext, binding: GLuint) {
+    unsafe {
+      gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.byte_buffer.handle.gl_id);
+    }
+  }
+
+  /// Point `shader`'s uniform block `block_name` at `binding`, and attach this buffer there.
+  pub fn bind_to_block(
+    &self,
+    gl: &mut GLContext,
+    shader: &mut Shader,
+    block_name: &str,
+    binding: GLuint,
+  ) -> Result<(), UniformError> {
+    if let Some(block) = shader.info.uniform_block(block_name) {
+      if (block.data_size as usize) > mem::size_of::<T>() {
+        return Err(UniformError::BlockTooSmall {
+          name: String::from(block_name),
+          required: block.data_size as usize,
+          given: mem::size_of::<T>(),
+        });
+      }
+    }
+
+    shader.bind_uniform_block(gl, block_name, binding)?;
+    self.bind_base(gl, binding);
+    Ok(())
+  }
+}
@@ -1,6 +1,7 @@
 use gl;
 use gl::types::*;
-use gl_context::GLContext;
+use gl_context::{GLContext, GlError};
+use index_buffer::{Index, IndexBuffer};
 use num;
 use std::ffi::CString;
 use std::os::raw::c_void;
@@ -53,6 +54,8 @@ impl<'a> Drop for BufferHandle<'a> {
 /// Fixed-size VRAM buffer for individual bytes.
 pub struct GLByteBuffer<'a> {
   pub handle: BufferHandle<'a>,
+  /// The binding point this buffer is used through, e.g. gl::ARRAY_BUFFER.
+  pub target: GLenum,
   /// number of bytes in the buffer.
   pub length: usize,
   /// maximum number of bytes in the buffer.
@@ -65,36 +68,42 @@ impl<'a> GLByteBuffer<'a> {
   pub fn new<'b:'a>(
     gl: &'a mut GLContext,
     capacity: usize,
-  ) -> GLByteBuffer<'b> {
+  ) -> Result<GLByteBuffer<'b>, GlError> {
+    GLByteBuffer::with_target(gl, gl::ARRAY_BUFFER, capacity)
+  }
+
+  /// Like `new`, but the buffer is bound to (and updated through) `target` instead of gl::ARRAY_BUFFER.
+  pub fn with_target<'b:'a>(
+    gl: &'a mut GLContext,
+    target: GLenum,
+    capacity: usize,
+  ) -> Result<GLByteBuffer<'b>, GlError> {
     let handle = BufferHandle::new(gl);
 
     unsafe {
-      gl::BindBuffer(gl::ARRAY_BUFFER, handle.gl_id);
+      gl::BindBuffer(target, handle.gl_id);
 
       gl::BufferData(
-        gl::ARRAY_BUFFER,
+        target,
         capacity as GLsizeiptr,
         ptr::null(),
         gl::DYNAMIC_DRAW,
       );
     }
 
-    match gl.get_error() {
-      gl::NO_ERROR => {},
-      gl::OUT_OF_MEMORY => panic!("Out of VRAM"),
-      err => warn!("OpenGL error 0x{:x}", err),
-    }
+    gl.check_errors()?;
 
-    GLByteBuffer {
+    Ok(GLByteBuffer {
       handle: handle,
+      target: target,
       length: 0,
       capacity: capacity,
-    }
+    })
   }
 
   pub fn bind(&self, _: &mut GLContext) {
     unsafe {
-      gl::BindBuffer(gl::ARRAY_BUFFER, self.handle.gl_id);
+      gl::BindBuffer(self.target, self.handle.gl_id);
     }
   }
 
@@ -132,8 +141,8 @@ impl<'a> GLByteBuffer<'a> {
 
       unsafe {
         gl::CopyBufferSubData(
-          gl::ARRAY_BUFFER,
-          gl::ARRAY_BUFFER,
+          self.target,
+          self.target,
           self.length as GLintptr,
           i as GLintptr,
           count as GLsizeiptr,
@@ -159,7 +168,7 @@ impl<'a> GLByteBuffer<'a> {
     assert!(idx + count <= self.capacity);
 
     gl::BufferSubData(
-      gl::ARRAY_BUFFER,
+      self.target,
       idx as GLintptr,
       count as GLsizeiptr,
       mem::transmute(vs),
@@ -177,11 +186,11 @@ impl<'a, T> GLBuffer<'a, T> {
   pub fn new<'b:'a>(
     gl: &'a mut GLContext,
     capacity: usize,
-  ) -> GLBuffer<'b, T> {
-    GLBuffer {
-      byte_buffer: GLByteBuffer::new(gl, capacity * mem::size_of::<T>()),
+  ) -> Result<GLBuffer<'b, T>, GlError> {
+    Ok(GLBuffer {
+      byte_buffer: GLByteBuffer::new(gl, capacity * mem::size_of::<T>())?,
       phantom: PhantomData,
-    }
+    })
   }
 
   /// N.B. For performance reasons, this does NOT bind the buffer.
@@ -229,13 +238,21 @@ pub enum DrawMode {
 }
 
 impl DrawMode {
-  fn to_enum(&self) -> GLenum {
+  pub fn to_enum(&self) -> GLenum {
     match *self {
       DrawMode::Lines     => gl::LINES,
       DrawMode::Triangles => gl::TRIANGLES,
       DrawMode::Points    => gl::POINTS,
     }
   }
+
+  pub fn vertices_per_primitive(&self) -> usize {
+    match *self {
+      DrawMode::Lines     => 2,
+      DrawMode::Triangles => 3,
+      DrawMode::Points    => 1,
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -244,97 +261,338 @@ pub enum GLType {
   Float,
   UInt,
   Int,
+  Byte,
+  UByte,
+  Short,
+  UShort,
+  HalfFloat,
+  /// Read through `glVertexAttribLPointer`, into `double`-based shader inputs.
+  Double,
+  /// Four signed components packed into 32 bits: 10 bits each for x, y and z, and 2 for w.
+  /// The attribute's `size` must be 4.
+  Int2101010Rev,
+  /// Like `Int2101010Rev`, but unsigned.
+  UInt2101010Rev,
 }
 
 impl GLType {
+  /// The size of one component, in bytes.
+  /// For packed formats, this is the size of the whole attribute.
   pub fn size(&self) -> u32 {
     num::NumCast::from(
       match *self {
-        GLType::Float => mem::size_of::<GLfloat>(),
-        GLType::UInt  => mem::size_of::<GLuint>(),
-        GLType::Int   => mem::size_of::<GLint>(),
+        GLType::Float          => mem::size_of::<GLfloat>(),
+        GLType::UInt           => mem::size_of::<GLuint>(),
+        GLType::Int            => mem::size_of::<GLint>(),
+        GLType::Byte           => mem::size_of::<GLbyte>(),
+        GLType::UByte          => mem::size_of::<GLubyte>(),
+        GLType::Short          => mem::size_of::<GLshort>(),
+        GLType::UShort         => mem::size_of::<GLushort>(),
+        GLType::HalfFloat      => mem::size_of::<GLhalf>(),
+        GLType::Double         => mem::size_of::<GLdouble>(),
+        GLType::Int2101010Rev  => mem::size_of::<GLuint>(),
+        GLType::UInt2101010Rev => mem::size_of::<GLuint>(),
       })
     .unwrap()
   }
 
   pub fn gl_enum(&self) -> GLenum {
     match *self {
-      GLType::Float => gl::FLOAT,
-      GLType::UInt  => gl::UNSIGNED_INT,
-      GLType::Int   => gl::INT,
+      GLType::Float          => gl::FLOAT,
+      GLType::UInt           => gl::UNSIGNED_INT,
+      GLType::Int            => gl::INT,
+      GLType::Byte           => gl::BYTE,
+      GLType::UByte          => gl::UNSIGNED_BYTE,
+      GLType::Short          => gl::SHORT,
+      GLType::UShort         => gl::UNSIGNED_SHORT,
+      GLType::HalfFloat      => gl::HALF_FLOAT,
+      GLType::Double         => gl::DOUBLE,
+      GLType::Int2101010Rev  => gl::INT_2_10_10_10_REV,
+      GLType::UInt2101010Rev => gl::UNSIGNED_INT_2_10_10_10_REV,
     }
   }
 
+  /// Whether this can be read into integer shader inputs.
   pub fn is_integral(&self) -> bool {
     match *self {
-      GLType::Float => false,
-      GLType::UInt  => true,
-      GLType::Int   => true,
+      GLType::Float          => false,
+      GLType::UInt           => true,
+      GLType::Int            => true,
+      GLType::Byte           => true,
+      GLType::UByte          => true,
+      GLType::Short          => true,
+      GLType::UShort         => true,
+      GLType::HalfFloat      => false,
+      GLType::Double         => false,
+      GLType::Int2101010Rev  => false,
+      GLType::UInt2101010Rev => false,
     }
   }
+
+  /// Whether all of an attribute's components are packed into one value.
+  pub fn is_packed(&self) -> bool {
+    match *self {
+      GLType::Int2101010Rev | GLType::UInt2101010Rev => true,
+      _ => false,
+    }
+  }
+}
+
+/// Which vertex shader input an attribute feeds.
+#[derive(Debug, Copy, Clone)]
+pub enum AttribLocation<'a> {
+  /// Cooresponds to the shader's `input variable`. Looked up in each program the attribute is applied to.
+  Name(&'a str),
+  /// A location fixed in advance, with `layout(location = N)` or `LinkOptions::attrib_location`.
+  /// Layouts using only fixed locations work with any program that agrees on them.
+  Fixed(GLuint),
 }
 
 #[derive(Debug)]
 /// Specifies how to pass data from OpenGL to the vertex shaders.
 pub struct VertexAttribData<'a> {
-  /// Cooresponds to the shader's `input variable`.
-  pub name: &'a str,
+  pub location: AttribLocation<'a>,
   /// The size of this attribute, in the provided units.
   pub size: u32,
   pub unit: GLType,
   pub divisor: u32,
+  /// Byte offset of this attribute in each vertex.
+  /// If `None`, it directly follows the previous attribute, as if the vertex were tightly packed.
+  pub offset: Option<u32>,
+  /// Whether integer data is normalized to [0, 1] (or [-1, 1] if signed) and read as floats.
+  /// If false, integer data is read as integers.
+  pub normalized: bool,
 }
 
 impl<'a> VertexAttribData<'a> {
-  pub fn apply(attribs: &[VertexAttribData<'a>], _gl: &mut GLContext, shader: &Shader) -> u32 {
+  /// The size of this attribute, in bytes.
+  pub fn byte_size(&self) -> u32 {
+    if self.unit.is_packed() {
+      self.unit.size()
+    } else {
+      self.size * self.unit.size()
+    }
+  }
+
+  /// Apply `attribs` to vertices laid out as they describe.
+  /// Returns the size of each vertex.
+  pub fn apply(attribs: &[VertexAttribData<'a>], gl: &mut GLContext, shader: &Shader) -> u32 {
+    VertexLayout::new(attribs).apply(gl, shader)
+  }
+}
+
+/// Where each attribute is in a vertex, and how far apart vertices are.
+pub struct VertexLayout<'a> {
+  pub attribs: &'a [VertexAttribData<'a>],
+  /// Bytes from the start of one vertex to the start of the next.
+  /// If `None`, it's where the last attribute ends, which is right for tightly packed vertices.
+  pub stride: Option<u32>,
+}
+
+impl<'a> VertexLayout<'a> {
+  pub fn new(attribs: &'a [VertexAttribData<'a>]) -> VertexLayout<'a> {
+    VertexLayout {
+      attribs: attribs,
+      stride: None,
+    }
+  }
+
+  pub fn with_stride(attribs: &'a [VertexAttribData<'a>], stride: u32) -> VertexLayout<'a> {
+    VertexLayout {
+      attribs: attribs,
+      stride: Some(stride),
+    }
+  }
+
+  /// The byte offset of each attribute: its explicit offset, or else right after the previous attribute.
+  pub fn offsets(&self) -> Vec<u32> {
+    let mut offsets = Vec::new();
     let mut offset = 0;
-    let attrib_span = {
-      let mut attrib_span = 0;
-      for attrib in attribs.iter() {
-        attrib_span += attrib.size * attrib.unit.size();
+    for attrib in self.attribs {
+      if let Some(explicit_offset) = attrib.offset {
+        offset = explicit_offset;
+      }
+      offsets.push(offset);
+      offset += attrib.byte_size();
+    }
+    offsets
+  }
+
+  /// Where the last byte of any attribute ends.
+  pub fn span(&self) -> u32 {
+    self.offsets()
+      .iter()
+      .zip(self.attribs.iter())
+      .map(|(offset, attrib)| offset + attrib.byte_size())
+      .max()
+      .unwrap_or(0)
+  }
+
+  pub fn stride(&self) -> u32 {
+    self.stride.unwrap_or_else(|| self.span())
+  }
+
+  /// Whether every attribute follows directly after the previous one.
+  pub fn is_packed(&self) -> bool {
+    self.attribs.iter().all(|attrib| attrib.offset.is_none())
+  }
+
+  /// Check that this layout describes a `size`-byte vertex type.
+  pub fn check_vertex_size(&self, size: usize) -> Result<(), String> {
+    let stride = self.stride.unwrap_or(size as u32);
+    if stride as usize != size {
+      return Err(format!("the layout's stride is {} bytes, but the vertex type is {} bytes", stride, size));
+    }
+
+    for (offset, attrib) in self.offsets().iter().zip(self.attribs.iter()) {
+      if (offset + attrib.byte_size()) as usize > size {
+        return Err(format!(
+          "attribute {:?} ends at byte {}, past the end of the {}-byte vertex type",
+          attrib.location, offset + attrib.byte_size(), size,
+        ));
       }
-      attrib_span
-    };
+    }
+
+    // Without offsets or a stride, there's no way to describe padding.
+    if self.is_packed() && self.stride.is_none() && self.span() as usize != size {
+      return Err(format!(
+        "the attributes describe {} bytes, but the vertex type is {} bytes; \
+         if it has padding, give the attributes explicit offsets",
+        self.span(), size,
+      ));
+    }
 
-    for attrib in attribs {
+    Ok(())
+  }
+
+  /// Returns the stride.
+  pub fn apply(&self, _gl: &mut GLContext, shader: &Shader) -> u32 {
+    let stride = self.stride();
+    for (&offset, attrib) in self.offsets().iter().zip(self.attribs.iter()) {
       let shader_attrib =
-        glGetAttribLocation(
-          shader.handle.gl_id,
-          attrib.name
-        );
-      assert!(shader_attrib != -1, "shader attribute \"{}\" not found", attrib.name);
-      let shader_attrib = shader_attrib as GLuint;
+        match attrib.location {
+          AttribLocation::Name(name) => {
+            let shader_attrib = glGetAttribLocation(shader.handle.gl_id, name);
+            assert!(shader_attrib != -1, "shader attribute \"{}\" not found", name);
+            shader_attrib as GLuint
+          },
+          AttribLocation::Fixed(location) => location,
+        };
 
       unsafe {
         gl::EnableVertexAttribArray(shader_attrib);
         gl::VertexAttribDivisor(shader_attrib, attrib.divisor);
 
-        if attrib.unit.is_integral() {
+        if let GLType::Double = attrib.unit {
+          gl::VertexAttribLPointer(
+            shader_attrib,
+            attrib.size as i32,
+            attrib.unit.gl_enum(),
+            stride as i32,
+            (ptr::null() as *const c_void).offset(offset as isize),
+          );
+        } else if attrib.unit.is_integral() && !attrib.normalized {
           gl::VertexAttribIPointer(
             shader_attrib,
             attrib.size as i32,
             attrib.unit.gl_enum(),
-            attrib_span as i32,
-            (ptr::null() as *const c_void).offset(offset),
+            stride as i32,
+            (ptr::null() as *const c_void).offset(offset as isize),
           );
         } else {
           gl::VertexAttribPointer(
             shader_attrib,
             attrib.size as i32,
             attrib.unit.gl_enum(),
-            gl::FALSE as GLboolean,
-            attrib_span as i32,
-            (ptr::null() as *const c_void).offset(offset),
+            attrib.normalized as GLboolean,
+            stride as i32,
+            (ptr::null() as *const c_void).offset(offset as isize),
           );
         }
       }
-      offset += (attrib.size * attrib.unit.size()) as isize;
     }
 
-    attrib_span
+    stride
   }
 }
 
+/// A Rust type that can be one vertex attribute: a scalar, or an array of up to 4 of them.
+pub unsafe trait VertexAttribType {
+  fn unit() -> GLType;
+  /// Number of components.
+  fn size() -> u32;
+}
+
+macro_rules! vertex_attrib_type {
+  ($t:ty, $unit:ident) => {
+    unsafe impl VertexAttribType for $t {
+      fn unit() -> GLType { GLType::$unit }
+      fn size() -> u32 { 1 }
+    }
+
+    unsafe impl VertexAttribType for [$t; 1] {
+      fn unit() -> GLType { GLType::$unit }
+      fn size() -> u32 { 1 }
+    }
+
+    unsafe impl VertexAttribType for [$t; 2] {
+      fn unit() -> GLType { GLType::$unit }
+      fn size() -> u32 { 2 }
+    }
+
+    unsafe impl VertexAttribType for [$t; 3] {
+      fn unit() -> GLType { GLType::$unit }
+      fn size() -> u32 { 3 }
+    }
+
+    unsafe impl VertexAttribType for [$t; 4] {
+      fn unit() -> GLType { GLType::$unit }
+      fn size() -> u32 { 4 }
+    }
+  }
+}
+
+/// A 16-bit float, as raw bits. Rust has no native half-precision type.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Half(pub u16);
+
+vertex_attrib_type!(f32, Float);
+vertex_attrib_type!(u32, UInt);
+vertex_attrib_type!(i32, Int);
+vertex_attrib_type!(i8, Byte);
+vertex_attrib_type!(u8, UByte);
+vertex_attrib_type!(i16, Short);
+vertex_attrib_type!(u16, UShort);
+vertex_attrib_type!(Half, HalfFloat);
+vertex_attrib_type!(f64, Double);
+
+/// Four signed components packed as `GL_INT_2_10_10_10_REV`, e.g. a compressed normal.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Int2101010Rev(pub u32);
+
+/// Four unsigned components packed as `GL_UNSIGNED_INT_2_10_10_10_REV`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UInt2101010Rev(pub u32);
+
+unsafe impl VertexAttribType for Int2101010Rev {
+  fn unit() -> GLType { GLType::Int2101010Rev }
+  fn size() -> u32 { 4 }
+}
+
+unsafe impl VertexAttribType for UInt2101010Rev {
+  fn unit() -> GLType { GLType::UInt2101010Rev }
+  fn size() -> u32 { 4 }
+}
+
+/// A `#[repr(C)]` vertex type that knows its own attribute layout.
+/// Usually implemented with `#[derive(Vertex)]` from `yaglw_macros`.
+pub unsafe trait Vertex: Copy {
+  fn attribs() -> Vec<VertexAttribData<'static>>;
+}
+
 pub struct ArrayHandle<'a> {
   pub gl_id: GLuint,
   phantom: PhantomData<&'a ()>,
@@ -381,31 +639,58 @@ impl<'a, T> GLArray<'a, T> {
     attribs: &[VertexAttribData],
     mode: DrawMode,
     buffer: GLBuffer<'b, T>,
-  ) -> GLArray<'b, T> {
+  ) -> Result<GLArray<'b, T>, GlError> {
+    GLArray::with_layout(gl, shader_program, &VertexLayout::new(attribs), mode, buffer)
+  }
+
+  /// Like `new`, but with an explicit layout.
+  /// Panics if the layout doesn't fit `T`.
+  pub fn with_layout<'b:'a>(
+    gl: &'a mut GLContext,
+    shader_program: &Shader<'b>,
+    layout: &VertexLayout,
+    mode: DrawMode,
+    buffer: GLBuffer<'b, T>,
+  ) -> Result<GLArray<'b, T>, GlError> {
+    if let Err(err) = layout.check_vertex_size(mem::size_of::<T>()) {
+      panic!("GLArray attribs don't describe the right number of bytes: {}", err);
+    }
+
     let handle = ArrayHandle::new(gl);
 
     unsafe {
       gl::BindVertexArray(handle.gl_id);
     }
 
-    let attrib_span = VertexAttribData::apply(attribs, gl, shader_program);
-    if attrib_span as usize != mem::size_of::<T>() {
-      panic!("GLArray attribs don't describe the right number of bytes");
-    }
+    VertexLayout {
+      attribs: layout.attribs,
+      stride: Some(mem::size_of::<T>() as u32),
+    }.apply(gl, shader_program);
 
-    match unsafe { gl::GetError() } {
-      gl::NO_ERROR => {},
-      err => warn!("OpenGL error 0x{:x}", err),
-    }
+    gl.check_errors()?;
 
     let length = buffer.byte_buffer.length / mem::size_of::<T>();
 
-    GLArray {
+    Ok(GLArray {
       buffer: buffer,
       handle: handle,
       mode: mode.to_enum(),
       length: length,
-    }
+    })
+  }
+
+  /// Like `new`, but with the attribute layout `T` describes.
+  pub fn from_vertex<'b:'a>(
+    gl: &'a mut GLContext,
+    shader_program: &Shader<'b>,
+    mode: DrawMode,
+    buffer: GLBuffer<'b, T>,
+  ) -> Result<GLArray<'b, T>, GlError>
+    where T: Vertex,
+  {
+    let attribs = T::attribs();
+    let layout = VertexLayout::with_stride(&attribs, mem::size_of::<T>() as u32);
+    GLArray::with_layout(gl, shader_program, &layout, mode, buffer)
   }
 
   pub fn bind(&self, _: &mut GLContext) {
@@ -434,6 +719,15 @@ impl<'a, T> GLArray<'a, T> {
     self.length -= count;
   }
 
+  /// Set the number of `T`s in the array, after the GPU has written to its buffer
+  /// (e.g. with transform feedback; see `TransformFeedback::vertices_written`).
+  pub fn set_length(&mut self, length: usize) {
+    let bytes = length * mem::size_of::<T>();
+    assert!(bytes <= self.buffer.byte_buffer.capacity);
+    self.buffer.byte_buffer.length = bytes;
+    self.length = length;
+  }
+
   /// Draws all the queued triangles to the screen.
   /// N.B. For performance reasons, this does NOT bind the array.
   /// It will do the wrong thing if `bind` has not been correctly called.
@@ -451,4 +745,298 @@ impl<'a, T> GLArray<'a, T> {
       gl::DrawArrays(self.mode, start as i32, len as i32);
     }
   }
+
+  /// Draw the whole array `instances` times.
+  /// Attributes with a nonzero `divisor` advance once every `divisor` instances.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn draw_instanced(&self, gl: &mut GLContext, instances: usize) {
+    self.draw_slice_instanced(gl, 0, self.length, instances, 0);
+  }
+
+  /// Draw some subset of the array `instances` times.
+  /// Instanced attributes start at instance `base_instance` (which needs OpenGL 4.2 if nonzero).
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` has not been correctly called.
+  pub fn draw_slice_instanced(
+    &self,
+    _gl: &mut GLContext,
+    start: usize,
+    len: usize,
+    instances: usize,
+    base_instance: usize,
+  ) {
+    assert!(start + len <= self.length);
+
+    unsafe {
+      if base_instance == 0 {
+        gl::DrawArraysInstanced(self.mode, start as GLint, len as GLsizei, instances as GLsizei);
+      } else {
+        assert!(
+          gl::DrawArraysInstancedBaseInstance::is_loaded(),
+          "a nonzero base_instance needs OpenGL 4.2 or ARB_base_instance",
+        );
+        gl::DrawArraysInstancedBaseInstance(
+          self.mode,
+          start as GLint,
+          len as GLsizei,
+          instances as GLsizei,
+          base_instance as GLuint,
+        );
+      }
+    }
+  }
+
+  /// Make `indices` this array's element buffer, for the `draw_elements` family.
+  /// This binds the array.
+  pub fn attach_indices<I: Index>(&self, _gl: &mut GLContext, indices: &IndexBuffer<I>) {
+    unsafe {
+      gl::BindVertexArray(self.handle.gl_id);
+      gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, indices.byte_buffer.handle.gl_id);
+    }
+  }
+
+  /// Draw every vertex referenced by `indices`.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements<I: Index>(&self, gl: &mut GLContext, indices: &IndexBuffer<I>) {
+    self.draw_elements_slice(gl, indices, 0, indices.length);
+  }
+
+  /// Draw the vertices referenced by `len` indices, starting at index `start`.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_slice<I: Index>(
+    &self,
+    _gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    start: usize,
+    len: usize,
+  ) {
+    assert!(start + len <= indices.length);
+
+    unsafe {
+      gl::DrawElements(
+        self.mode,
+        len as GLsizei,
+        I::gl_enum(),
+        (ptr::null() as *const c_void).offset((start * mem::size_of::<I>()) as isize),
+      );
+    }
+  }
+
+  /// Draw every vertex referenced by `indices`, `instances` times.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_instanced<I: Index>(
+    &self,
+    gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    instances: usize,
+  ) {
+    self.draw_elements_slice_instanced(gl, indices, 0, indices.length, instances, 0);
+  }
+
+  /// Draw the vertices referenced by `len` indices, starting at index `start`, `instances` times.
+  /// Instanced attributes start at instance `base_instance` (which needs OpenGL 4.2 if nonzero).
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_slice_instanced<I: Index>(
+    &self,
+    _gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    start: usize,
+    len: usize,
+    instances: usize,
+    base_instance: usize,
+  ) {
+    assert!(start + len <= indices.length);
+
+    unsafe {
+      let offset = (ptr::null() as *const c_void).offset((start * mem::size_of::<I>()) as isize);
+      if base_instance == 0 {
+        gl::DrawElementsInstanced(
+          self.mode,
+          len as GLsizei,
+          I::gl_enum(),
+          offset,
+          instances as GLsizei,
+        );
+      } else {
+        assert!(
+          gl::DrawElementsInstancedBaseInstance::is_loaded(),
+          "a nonzero base_instance needs OpenGL 4.2 or ARB_base_instance",
+        );
+        gl::DrawElementsInstancedBaseInstance(
+          self.mode,
+          len as GLsizei,
+          I::gl_enum(),
+          offset,
+          instances as GLsizei,
+          base_instance as GLuint,
+        );
+      }
+    }
+  }
+
+  /// Like `draw_elements_slice`, but promises the driver that every index is in `[min_vertex, max_vertex]`,
+  /// which can let it fetch vertices more efficiently.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_range<I: Index>(
+    &self,
+    _gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    start: usize,
+    len: usize,
+    min_vertex: usize,
+    max_vertex: usize,
+  ) {
+    assert!(start + len <= indices.length);
+    assert!(min_vertex <= max_vertex && max_vertex < self.length);
+
+    unsafe {
+      gl::DrawRangeElements(
+        self.mode,
+        min_vertex as GLuint,
+        max_vertex as GLuint,
+        len as GLsizei,
+        I::gl_enum(),
+        (ptr::null() as *const c_void).offset((start * mem::size_of::<I>()) as isize),
+      );
+    }
+  }
+
+  /// Like `draw_elements_slice`, but `base_vertex` (which may be negative) is added to every index.
+  /// This lets small index types address vertices far into the array.
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_base_vertex<I: Index>(
+    &self,
+    _gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    start: usize,
+    len: usize,
+    base_vertex: GLint,
+  ) {
+    assert!(start + len <= indices.length);
+
+    unsafe {
+      gl::DrawElementsBaseVertex(
+        self.mode,
+        len as GLsizei,
+        I::gl_enum(),
+        (ptr::null() as *const c_void).offset((start * mem::size_of::<I>()) as isize),
+        base_vertex,
+      );
+    }
+  }
+
+  /// `draw_elements_slice_instanced` and `draw_elements_base_vertex` together: `base_vertex` (which may be negative)
+  /// is added to every index, and instanced attributes start at instance `base_instance` (which needs OpenGL 4.2 if nonzero).
+  /// N.B. For performance reasons, this does NOT bind the array.
+  /// It will do the wrong thing if `bind` and `attach_indices` have not been correctly called.
+  pub fn draw_elements_instanced_base_vertex<I: Index>(
+    &self,
+    _gl: &mut GLContext,
+    indices: &IndexBuffer<I>,
+    start: usize,
+    len: usize,
+    base_vertex: GLint,
+    instances: usize,
+    base_instance: usize,
+  ) {
+    assert!(start + len <= indices.length);
+
+    unsafe {
+      let offset = (ptr::null() as *const c_void).offset((start * mem::size_of::<I>()) as isize);
+      if base_instance == 0 {
+        gl::DrawElementsInstancedBaseVertex(
+          self.mode,
+          len as GLsizei,
+          I::gl_enum(),
+          offset,
+          instances as GLsizei,
+          base_vertex,
+        );
+      } else {
+        assert!(
+          gl::DrawElementsInstancedBaseVertexBaseInstance::is_loaded(),
+          "a nonzero base_instance needs OpenGL 4.2 or ARB_base_instance",
+        );
+        gl::DrawElementsInstancedBaseVertexBaseInstance(
+          self.mode,
+          len as GLsizei,
+          I::gl_enum(),
+          offset,
+          instances as GLsizei,
+          base_vertex,
+          base_instance as GLuint,
+        );
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn attrib<'a>(name: &'a str, size: u32, unit: GLType, offset: Option<u32>) -> VertexAttribData<'a> {
+    VertexAttribData {
+      location: AttribLocation::Name(name),
+      size: size,
+      unit: unit,
+      divisor: 0,
+      offset: offset,
+      normalized: false,
+    }
+  }
+
+  #[test]
+  fn packed() {
+    let attribs = [
+      attrib("position", 3, GLType::Float, None),
+      attrib("normal", 4, GLType::Int2101010Rev, None),
+      attrib("color", 4, GLType::UByte, None),
+    ];
+    let layout = VertexLayout::new(&attribs);
+    assert_eq!(layout.offsets(), [0, 12, 16]);
+    assert_eq!(layout.span(), 20);
+    assert_eq!(layout.stride(), 20);
+    assert!(layout.check_vertex_size(20).is_ok());
+    // Trailing padding can't be described without offsets or a stride.
+    assert!(layout.check_vertex_size(24).is_err());
+  }
+
+  #[test]
+  fn explicit_offsets_with_padding() {
+    let attribs = [
+      attrib("position", 3, GLType::Float, Some(0)),
+      attrib("uv", 2, GLType::Float, Some(16)),
+      attrib("layer", 1, GLType::UInt, None),
+    ];
+    let layout = VertexLayout::with_stride(&attribs, 32);
+    assert_eq!(layout.offsets(), [0, 16, 24]);
+    assert_eq!(layout.span(), 28);
+    assert_eq!(layout.stride(), 32);
+    assert!(layout.check_vertex_size(32).is_ok());
+  }
+
+  #[test]
+  fn stride_mismatch() {
+    let attribs = [attrib("position", 3, GLType::Float, Some(0))];
+    assert!(VertexLayout::with_stride(&attribs, 12).check_vertex_size(16).is_err());
+    assert!(VertexLayout::with_stride(&attribs, 16).check_vertex_size(16).is_ok());
+  }
+
+  #[test]
+  fn attribute_overruns_vertex() {
+    let attribs = [
+      attrib("position", 3, GLType::Float, Some(0)),
+      attrib("color", 4, GLType::Float, Some(12)),
+    ];
+    assert!(VertexLayout::with_stride(&attribs, 24).check_vertex_size(24).is_err());
+    assert!(VertexLayout::with_stride(&attribs, 28).check_vertex_size(28).is_ok());
+  }
 }
@@ -0,0 +1,75 @@
+//! `#[derive(Vertex)]` only generates attribute descriptions, so this doesn't need a GL context.
+
+extern crate gl;
+extern crate yaglw;
+
+use std::mem;
+
+use yaglw::Vertex;
+use yaglw::vertex_buffer::{AttribLocation, Vertex, VertexAttribData};
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Vertex)]
+#[repr(C)]
+struct Particle {
+  position: [f32; 3],
+  #[vertex(skip)]
+  _padding: u8,
+  #[vertex(normalized)]
+  color: [u8; 4],
+  #[vertex(name = "instance_scale", divisor = 1)]
+  scale: f32,
+  #[vertex(location = 3)]
+  layer: u16,
+}
+
+fn name<'a>(attrib: &VertexAttribData<'a>) -> Option<&'a str> {
+  match attrib.location {
+    AttribLocation::Name(name) => Some(name),
+    AttribLocation::Fixed(_) => None,
+  }
+}
+
+fn fixed(attrib: &VertexAttribData) -> Option<u32> {
+  match attrib.location {
+    AttribLocation::Name(_) => None,
+    AttribLocation::Fixed(location) => Some(location),
+  }
+}
+
+#[test]
+fn attributes() {
+  let attribs = Particle::attribs();
+  assert_eq!(attribs.len(), 4);
+
+  assert_eq!(name(&attribs[0]), Some("position"));
+  assert_eq!(attribs[0].size, 3);
+  assert_eq!(attribs[0].unit.gl_enum(), gl::FLOAT);
+
+  assert_eq!(name(&attribs[1]), Some("color"));
+  assert_eq!(attribs[1].size, 4);
+  assert_eq!(attribs[1].unit.gl_enum(), gl::UNSIGNED_BYTE);
+
+  assert_eq!(name(&attribs[2]), Some("instance_scale"));
+  assert_eq!(fixed(&attribs[3]), Some(3));
+  assert_eq!(attribs[3].unit.gl_enum(), gl::UNSIGNED_SHORT);
+
+  let divisors: Vec<u32> = attribs.iter().map(|a| a.divisor).collect();
+  assert_eq!(divisors, [0, 0, 1, 0]);
+  let normalized: Vec<bool> = attribs.iter().map(|a| a.normalized).collect();
+  assert_eq!(normalized, [false, true, false, false]);
+}
+
+#[test]
+fn offsets() {
+  // `color` follows the skipped byte, and `scale` is aligned past the padding after it.
+  let expected = [
+    mem::offset_of!(Particle, position),
+    mem::offset_of!(Particle, color),
+    mem::offset_of!(Particle, scale),
+    mem::offset_of!(Particle, layer),
+  ];
+  let offsets: Vec<usize> = Particle::attribs().iter().map(|a| a.offset.unwrap() as usize).collect();
+  assert_eq!(offsets, expected);
+  assert_eq!(expected, [0, 13, 20, 24]);
+}
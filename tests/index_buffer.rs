@@ -0,0 +1,109 @@
+//! Needs a display. Runs without the test harness, so that SDL is initialized on the main thread.
+
+extern crate gl;
+extern crate sdl2;
+extern crate yaglw;
+
+use gl::types::*;
+use std::os::raw::c_void;
+
+use yaglw::gl_context::GLContext;
+use yaglw::index_buffer::IndexBuffer;
+
+fn read_indices(indices: &IndexBuffer<u16>) -> Vec<u16> {
+  let mut is = vec!(0; indices.length);
+  unsafe {
+    gl::GetBufferSubData(
+      gl::COPY_WRITE_BUFFER,
+      0,
+      (is.len() * 2) as GLsizeiptr,
+      is.as_mut_ptr() as *mut c_void,
+    );
+  }
+  is
+}
+
+fn get_integer(name: GLenum) -> GLuint {
+  let mut value = 0;
+  unsafe {
+    gl::GetIntegerv(name, &mut value);
+  }
+  value as GLuint
+}
+
+fn bookkeeping(gl: &mut GLContext) {
+  let mut indices = IndexBuffer::<u16>::new(gl, 6).unwrap();
+  indices.bind(gl);
+
+  assert!(indices.push(gl, &[0, 1, 2, 3]));
+  assert_eq!(indices.length, 4);
+
+  // Doesn't fit, so nothing changes.
+  assert!(!indices.push(gl, &[4, 5, 6]));
+  assert_eq!(indices.length, 4);
+
+  indices.update(gl, 1, &[7, 8]);
+  assert_eq!(indices.length, 4);
+  assert_eq!(read_indices(&indices), [0, 7, 8, 3]);
+
+  // The last index moves into the hole.
+  indices.swap_remove(gl, 0, 1);
+  assert_eq!(indices.length, 3);
+  assert_eq!(read_indices(&indices), [3, 7, 8]);
+
+  indices.swap_remove(gl, 2, 1);
+  assert_eq!(indices.length, 2);
+  assert_eq!(read_indices(&indices), [3, 7]);
+
+  assert!(indices.push(gl, &[9, 10, 11, 12]));
+  assert_eq!(indices.length, 6);
+  assert_eq!(read_indices(&indices), [3, 7, 9, 10, 11, 12]);
+}
+
+fn leaves_vertex_array_alone(gl: &mut GLContext) {
+  let attached = IndexBuffer::<u16>::new(gl, 3).unwrap();
+
+  let mut vao = 0;
+  unsafe {
+    gl::GenVertexArrays(1, &mut vao);
+    gl::BindVertexArray(vao);
+    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, attached.byte_buffer.handle.gl_id);
+  }
+
+  let mut indices = IndexBuffer::<u16>::new(gl, 3).unwrap();
+  indices.bind(gl);
+  assert!(indices.push(gl, &[0, 1, 2]));
+  indices.update(gl, 0, &[3]);
+
+  assert_eq!(get_integer(gl::VERTEX_ARRAY_BINDING), vao);
+  assert_eq!(get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING), attached.byte_buffer.handle.gl_id);
+
+  unsafe {
+    gl::BindVertexArray(0);
+    gl::DeleteVertexArrays(1, &vao);
+  }
+}
+
+fn main() {
+  let sdl = sdl2::init().unwrap();
+  let video = sdl.video().unwrap();
+
+  {
+    let gl_attr = video.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(3, 3);
+  }
+
+  let mut window = video.window("index_buffer", 64, 64);
+  window.opengl();
+  window.hidden();
+  let window = window.build().unwrap();
+  let sdl_gl_context = window.gl_create_context().unwrap();
+
+  let mut gl = GLContext::from_sdl2(&video, &sdl_gl_context).unwrap();
+
+  bookkeeping(&mut gl);
+  leaves_vertex_array_alone(&mut gl);
+
+  gl.check_errors().unwrap();
+}
@@ -0,0 +1,346 @@
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, DeriveInput, Field, Ident, Lit, LitStr, Meta, NestedMeta};
+
+/// Shader stages, named by their conventional file extensions.
+const STAGES: &'static [&'static str] = &["vert", "frag", "geom", "tesc", "tese", "comp"];
+
+/// Set this environment variable to make validation fail when `glslangValidator` can't be found,
+/// instead of letting shaders through unchecked.
+const REQUIRE_VAR: &'static str = "YAGLW_REQUIRE_SHADER_VALIDATION";
+
+fn error(span: Span, message: &str) -> TokenStream {
+  syn::Error::new(span, message).to_compile_error().into()
+}
+
+/// Run `source` through `glslangValidator`. Returns its log on failure,
+/// and `Ok(false)` if it couldn't be checked because `glslangValidator` isn't installed.
+fn validate(stage: &str, source: &str) -> Result<bool, String> {
+  let child =
+    Command::new("glslangValidator")
+      .args(&["--stdin", "-S", stage])
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn();
+  let mut child =
+    match child {
+      Ok(child) => child,
+      Err(err) => {
+        if env::var_os(REQUIRE_VAR).is_some() {
+          return Err(format!("couldn't run glslangValidator: {}", err));
+        }
+        return Ok(false);
+      },
+    };
+
+  child.stdin.take().unwrap().write_all(source.as_bytes()).map_err(|e| e.to_string())?;
+  let output = child.wait_with_output().map_err(|e| e.to_string())?;
+  if output.status.success() {
+    return Ok(true);
+  }
+
+  let mut log = String::from_utf8_lossy(&output.stdout).into_owned();
+  log.push_str(&String::from_utf8_lossy(&output.stderr));
+  Err(format!("{} shader failed to validate:\n{}", stage, log.trim_end()))
+}
+
+/// Wrap `expr` so that it compiles with a warning that it wasn't validated.
+/// Proc macros can't emit warnings on stable Rust, so this refers to a deprecated item instead.
+fn unchecked(span: Span, expr: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+  let note =
+    format!(
+      "shader not validated: glslangValidator isn't on PATH (set {} to make this an error)",
+      REQUIRE_VAR,
+    );
+  quote_spanned! {span=>
+    {
+      #[deprecated(note = #note)]
+      #[allow(non_upper_case_globals)]
+      const shader_not_validated: () = ();
+      const _: () = shader_not_validated;
+      #expr
+    }
+  }
+}
+
+struct ValidateShader {
+  stage: Ident,
+  source: LitStr,
+}
+
+impl Parse for ValidateShader {
+  fn parse(input: ParseStream) -> syn::Result<ValidateShader> {
+    let stage = input.parse()?;
+    input.parse::<Token![,]>()?;
+    let source = input.parse()?;
+    Ok(ValidateShader {
+      stage: stage,
+      source: source,
+    })
+  }
+}
+
+/// `validate_shader!(frag, "...")` checks an embedded GLSL string with `glslangValidator` at build time,
+/// and expands to the string. The stage is one of `vert`, `frag`, `geom`, `tesc`, `tese` or `comp`.
+///
+/// If `glslangValidator` isn't on `PATH`, the string is let through unchecked with a warning,
+/// or fails to compile if `YAGLW_REQUIRE_SHADER_VALIDATION` is set.
+#[proc_macro]
+pub fn validate_shader(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as ValidateShader);
+
+  let stage = input.stage.to_string();
+  if !STAGES.contains(&&stage[..]) {
+    return error(
+      input.stage.span(),
+      &format!("unknown shader stage `{}`; expected one of {}", stage, STAGES.join(", ")),
+    );
+  }
+
+  let source = input.source;
+  match validate(&stage, &source.value()) {
+    Ok(true) => (quote! { #source }).into(),
+    Ok(false) => unchecked(source.span(), quote! { #source }).into(),
+    Err(log) => error(source.span(), &log),
+  }
+}
+
+/// `include_shader!("shaders/light.frag")` is `include_str!` for shaders: the file is checked like `validate_shader!`,
+/// with its stage inferred from its extension. Paths are relative to the crate root (`CARGO_MANIFEST_DIR`).
+#[proc_macro]
+pub fn include_shader(input: TokenStream) -> TokenStream {
+  let path_lit = parse_macro_input!(input as LitStr);
+
+  let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+  let path = Path::new(&root).join(path_lit.value());
+
+  let stage = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+  if !STAGES.contains(&stage) {
+    return error(
+      path_lit.span(),
+      &format!("can't tell the shader stage of {} from its extension", path.display()),
+    );
+  }
+
+  let source =
+    match fs::read_to_string(&path) {
+      Ok(source) => source,
+      Err(err) => return error(path_lit.span(), &format!("couldn't read {}: {}", path.display(), err)),
+    };
+
+  let checked =
+    match validate(stage, &source) {
+      Ok(checked) => checked,
+      Err(log) => return error(path_lit.span(), &format!("{}: {}", path.display(), log)),
+    };
+
+  // `include_str!` makes cargo rebuild when the file changes.
+  let path = path.to_string_lossy().into_owned();
+  let expr = quote! { include_str!(#path) };
+  if checked {
+    expr.into()
+  } else {
+    unchecked(path_lit.span(), expr).into()
+  }
+}
+
+fn is_repr_c(attrs: &[Attribute]) -> bool {
+  attrs.iter().any(|attr| {
+    if !attr.path.is_ident("repr") {
+      return false;
+    }
+    match attr.parse_meta() {
+      Ok(Meta::List(list)) =>
+        list.nested.iter().any(|nested| {
+          match *nested {
+            NestedMeta::Meta(Meta::Path(ref path)) => path.is_ident("C"),
+            _ => false,
+          }
+        }),
+      _ => false,
+    }
+  })
+}
+
+/// Options from a field's `#[vertex(...)]` attributes.
+struct FieldOptions {
+  name: Option<String>,
+  location: Option<u32>,
+  divisor: u32,
+  normalized: bool,
+  skip: bool,
+}
+
+fn parse_u32(lit: &Lit) -> syn::Result<u32> {
+  match *lit {
+    Lit::Int(ref i) => i.base10_parse(),
+    _ => Err(syn::Error::new_spanned(lit, "expected an integer")),
+  }
+}
+
+fn field_options(field: &Field) -> syn::Result<FieldOptions> {
+  let mut options = FieldOptions {
+    name: None,
+    location: None,
+    divisor: 0,
+    normalized: false,
+    skip: false,
+  };
+
+  for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("vertex")) {
+    let list =
+      match attr.parse_meta()? {
+        Meta::List(list) => list,
+        meta => return Err(syn::Error::new_spanned(meta, "expected #[vertex(...)]")),
+      };
+
+    for nested in list.nested.iter() {
+      match *nested {
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("normalized") =>
+          options.normalized = true,
+        NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") =>
+          options.skip = true,
+        NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("name") =>
+          match nv.lit {
+            Lit::Str(ref s) => options.name = Some(s.value()),
+            ref lit => return Err(syn::Error::new_spanned(lit, "expected a string")),
+          },
+        NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("location") =>
+          options.location = Some(parse_u32(&nv.lit)?),
+        NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("divisor") =>
+          options.divisor = parse_u32(&nv.lit)?,
+        ref other =>
+          return Err(syn::Error::new_spanned(
+            other,
+            "unknown vertex option; expected name, location, divisor, normalized or skip",
+          )),
+      }
+    }
+  }
+
+  Ok(options)
+}
+
+fn vertex_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+  let ident = &input.ident;
+
+  if !input.generics.params.is_empty() {
+    return Err(syn::Error::new_spanned(&input.generics, "#[derive(Vertex)] doesn't support generic types"));
+  }
+  if !is_repr_c(&input.attrs) {
+    return Err(syn::Error::new_spanned(ident, "#[derive(Vertex)] needs #[repr(C)], so field offsets are stable"));
+  }
+
+  let fields =
+    match input.data {
+      syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => &fields.named,
+      _ => return Err(syn::Error::new_spanned(ident, "#[derive(Vertex)] only supports structs with named fields")),
+    };
+
+  let mut attribs = Vec::new();
+  for field in fields {
+    let options = field_options(field)?;
+    if options.skip {
+      continue;
+    }
+
+    let field_ident = field.ident.as_ref().unwrap();
+    let ty = &field.ty;
+    let location =
+      match options.location {
+        Some(location) => quote! { ::yaglw::vertex_buffer::AttribLocation::Fixed(#location) },
+        None => {
+          let name = options.name.unwrap_or_else(|| field_ident.to_string());
+          quote! { ::yaglw::vertex_buffer::AttribLocation::Name(#name) }
+        },
+      };
+    let divisor = options.divisor;
+    let normalized = options.normalized;
+
+    attribs.push(quote! {
+      ::yaglw::vertex_buffer::VertexAttribData {
+        location: #location,
+        size: <#ty as ::yaglw::vertex_buffer::VertexAttribType>::size(),
+        unit: <#ty as ::yaglw::vertex_buffer::VertexAttribType>::unit(),
+        divisor: #divisor,
+        offset: Some(unsafe { ::std::ptr::addr_of!((*base).#field_ident) as usize - base as usize } as u32),
+        normalized: #normalized,
+      }
+    });
+  }
+
+  Ok(quote! {
+    unsafe impl ::yaglw::vertex_buffer::Vertex for #ident {
+      #[allow(unused_variables)]
+      fn attribs() -> ::std::vec::Vec<::yaglw::vertex_buffer::VertexAttribData<'static>> {
+        // Field offsets are taken without creating a value, since not every field type can be zeroed.
+        let value = ::std::mem::MaybeUninit::<#ident>::uninit();
+        let base = value.as_ptr();
+        vec!(#(#attribs),*)
+      }
+    }
+  })
+}
+
+/// `#[derive(Vertex)]` implements `yaglw::vertex_buffer::Vertex` for a `#[repr(C)]` struct.
+/// Each field becomes an attribute named after it, at its real offset in the struct.
+/// Fields can be annotated with `#[vertex(...)]`:
+///
+///   `name = "..."`: the shader input's name, if it isn't the field's.
+///   `location = N`: use a fixed attribute location instead of looking the name up.
+///   `divisor = N`: advance once every N instances, instead of once per vertex.
+///   `normalized`: read integers as normalized floats.
+///   `skip`: not an attribute, e.g. explicit padding.
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match vertex_impl(&input) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vertex_error(input: &str) -> String {
+    let input: DeriveInput = syn::parse_str(input).unwrap();
+    match vertex_impl(&input) {
+      Ok(_) => panic!("expected #[derive(Vertex)] to fail"),
+      Err(err) => err.to_string(),
+    }
+  }
+
+  #[test]
+  fn needs_repr_c() {
+    assert!(vertex_error("struct V { position: [f32; 3] }").contains("#[repr(C)]"));
+    assert!(vertex_error("#[repr(packed)] struct V { position: [f32; 3] }").contains("#[repr(C)]"));
+  }
+
+  #[test]
+  fn rejects_unknown_options() {
+    let err = vertex_error("#[repr(C)] struct V { #[vertex(colour)] color: [u8; 4] }");
+    assert!(err.contains("unknown vertex option"));
+  }
+
+  #[test]
+  fn rejects_non_structs() {
+    assert!(vertex_error("#[repr(C)] struct V([f32; 3]);").contains("named fields"));
+    assert!(vertex_error("#[repr(C)] struct V<T> { position: T }").contains("generic"));
+  }
+}